@@ -25,7 +25,11 @@ fn main() {
     // never did that, so the for loop is pointless...
     //
     // But I don't care to change it.
-    for a in local_args {
+    //
+    // Anything that isn't a flag is assumed to be a file that should be
+    // processed, so it's not totally pointless anymore.
+    let mut args = local_args.iter().skip(1);
+    while let Some(a) = args.next() {
         match a.as_str().trim() {
             "-h" => data.flags = Some(ss_data::Flags::Help),
             "--help" => data.flags = Some(ss_data::Flags::Help),
@@ -43,27 +47,173 @@ fn main() {
             "--test" => data.flags = Some(ss_data::Flags::Test),
             "-r" => data.flags = Some(ss_data::Flags::Review),
             "--review" => data.flags = Some(ss_data::Flags::Review),
-            _ => println!("Arg: {}", a),
+            "-w" => data.flags = Some(ss_data::Flags::Where),
+            "--where" => data.flags = Some(ss_data::Flags::Where),
+            "-m" | "--mode" => {
+                let mode = args.next().and_then(|m| structures::IngestMode::from_arg(m.trim()));
+                match mode {
+                    Some(m) => data.ingest_mode = m,
+                    None => {
+                        eprintln!("--mode needs to be followed by spaced, script or dictionary.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "--overwrite-glosses" => data.overwrite_glosses = true,
+            "--ime" => data.ime = true,
+            "--reverse" => data.reverse = true,
+            "--no-deinflect" => data.deinflect = false,
+            "-f" => data.flags = Some(ss_data::Flags::Search),
+            "--search" => data.flags = Some(ss_data::Flags::Search),
+            "-sa" => data.flags = Some(ss_data::Flags::StopAdd),
+            "--stop-add" => data.flags = Some(ss_data::Flags::StopAdd),
+            "-sr" => data.flags = Some(ss_data::Flags::StopRemove),
+            "--stop-remove" => data.flags = Some(ss_data::Flags::StopRemove),
+            "-sl" => data.flags = Some(ss_data::Flags::StopList),
+            "--stop-list" => data.flags = Some(ss_data::Flags::StopList),
+            "-c" => data.flags = Some(ss_data::Flags::Conjugate),
+            "--conjugate" => data.flags = Some(ss_data::Flags::Conjugate),
+            "-l" => data.flags = Some(ss_data::Flags::Lessons),
+            "--lessons" => data.flags = Some(ss_data::Flags::Lessons),
+            "--lesson" => {
+                match args.next().and_then(|l| dates::parse_arg(l.trim())) {
+                    Some(l) => data.filter.lesson = Some(l),
+                    None => {
+                        eprintln!("--lesson needs to be followed by a date, like 6/21.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "--romaji" => {
+                match args.next().and_then(|r| romaji::System::from_arg(r.trim())) {
+                    Some(r) => data.romaji = r,
+                    None => {
+                        eprintln!("--romaji needs to be followed by hepburn or kunrei.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "--tag" => {
+                match args.next().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                    Some(t) => data.filter.tags.push(t.to_string()),
+                    None => {
+                        eprintln!("--tag needs to be followed by a tag, like colors.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "-ta" => data.flags = Some(ss_data::Flags::TagAdd),
+            "--tag-add" => data.flags = Some(ss_data::Flags::TagAdd),
+            "-tr" => data.flags = Some(ss_data::Flags::TagRemove),
+            "--tag-remove" => data.flags = Some(ss_data::Flags::TagRemove),
+            "-mg" => data.flags = Some(ss_data::Flags::Migrate),
+            "--migrate" => data.flags = Some(ss_data::Flags::Migrate),
+            "-tl" => data.flags = Some(ss_data::Flags::TagList),
+            "--tag-list" => data.flags = Some(ss_data::Flags::TagList),
+            "-b" | "--backup" | "backup" => {
+                let command = args.next().map(|c| c.trim());
+                let n = match command {
+                    Some("restore") => args.next().and_then(|n| n.trim().parse::<usize>().ok()),
+                    _ => None,
+                };
+                match (command, n) {
+                    (Some("list"), _) => data.flags = Some(ss_data::Flags::BackupList),
+                    (Some("restore"), Some(n)) => data.flags = Some(ss_data::Flags::BackupRestore(n)),
+                    _ => {
+                        eprintln!("backup needs to be followed by list, or restore and a number");
+                        eprintln!("from the list, like backup restore 1.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "profile" => {
+                let command = args.next().map(|c| c.trim());
+                let mut name = || args.next().map(|n| n.trim().to_string());
+                data.flags = match command {
+                    Some("list") => Some(ss_data::Flags::ProfileList),
+                    Some("create") => name().map(ss_data::Flags::ProfileCreate),
+                    Some("delete") => name().map(ss_data::Flags::ProfileDelete),
+                    Some("copy") => name().zip(name()).map(|(from, to)| ss_data::Flags::ProfileCopy(from, to)),
+                    _ => None,
+                };
+                if data.flags.is_none() {
+                    eprintln!("profile needs to be followed by list, create <name>, copy <from> <to>");
+                    eprintln!("or delete <name>.");
+                    process::exit(1);
+                }
+            }
+            "--shared" => data.shared = true,
+            "--profile" => {
+                match args.next().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+                    Some(p) => data.profile = Some(p.to_string()),
+                    None => {
+                        eprintln!("--profile needs to be followed by a profile name.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "--data" => {
+                match args.next() {
+                    Some(f) => data.data_file = Some(f.clone()),
+                    None => {
+                        eprintln!("--data needs to be followed by a file.");
+                        process::exit(1);
+                    }
+                }
+            }
+            "--dict" => {
+                match args.next() {
+                    Some(f) => data.dict_file = Some(f.clone()),
+                    None => {
+                        eprintln!("--dict needs to be followed by a file.");
+                        process::exit(1);
+                    }
+                }
+            }
+            _ => data.file_args.get_or_insert_with(Vec::new).push(a.clone()),
         }
     }
 
+    // Figure out where the word list is before anything touches it.
+    config::init(data.data_file.as_deref(), data.profile.as_deref());
+
     // Based on the flag, call the right processes.
     match data.flags {
         Some(ss_data::Flags::Help) => processes::print_help(),
-        Some(ss_data::Flags::PrintAll) => processes::print_all(),
-        Some(ss_data::Flags::PrintRandom) => processes::print_random(),
-        Some(ss_data::Flags::AddMeaning) => processes::set_meaning(),
-        Some(ss_data::Flags::AddWord) => processes::add_word(),
+        Some(ss_data::Flags::PrintAll) => processes::print_all(&data.filter),
+        Some(ss_data::Flags::PrintRandom) => processes::print_random(&data.filter),
+        Some(ss_data::Flags::AddMeaning) => processes::set_meaning(&data),
+        Some(ss_data::Flags::AddWord) => processes::add_word(&data),
         Some(ss_data::Flags::RemoveWord) => processes::remove_word(),
-        Some(ss_data::Flags::Test) => processes::test(),
+        Some(ss_data::Flags::Test) => processes::test(&data),
         Some(ss_data::Flags::Review) => processes::review(),
-        None => processes::process_files().expect("Processing Failed"),
+        Some(ss_data::Flags::Where) => processes::show_where(),
+        Some(ss_data::Flags::Lessons) => processes::print_lessons(),
+        Some(ss_data::Flags::Search) => processes::search(),
+        Some(ss_data::Flags::StopAdd) => processes::stop_add(),
+        Some(ss_data::Flags::StopRemove) => processes::stop_remove(),
+        Some(ss_data::Flags::StopList) => processes::print_stop_list(),
+        Some(ss_data::Flags::Conjugate) => processes::conjugate_quiz(&data),
+        Some(ss_data::Flags::TagAdd) => processes::tag_words(&data, true),
+        Some(ss_data::Flags::TagRemove) => processes::tag_words(&data, false),
+        Some(ss_data::Flags::TagList) => processes::print_tags(),
+        Some(ss_data::Flags::Migrate) => processes::migrate_file(),
+        Some(ss_data::Flags::BackupList) => processes::list_backups(),
+        Some(ss_data::Flags::BackupRestore(n)) => processes::restore_backup(n),
+        Some(ss_data::Flags::ProfileList) => processes::list_profiles(),
+        Some(ss_data::Flags::ProfileCreate(ref n)) => processes::create_profile(n, data.shared),
+        Some(ss_data::Flags::ProfileCopy(ref from, ref to)) => processes::copy_profile(from, to),
+        Some(ss_data::Flags::ProfileDelete(ref n)) => processes::delete_profile(n),
+        None => processes::process_files(&data).expect("Processing Failed"),
     }
 }
 
 
 // Contains the structs neseccary for the program to execute correctly. 
 mod ss_data {
+    use crate::romaji;
+    use crate::structures::{IngestMode, JPWord, LessonDate};
+
     pub enum Flags {
         Help,
         PrintAll,
@@ -73,121 +223,638 @@ mod ss_data {
         RemoveWord,
         Test,
         Review,
+        Where,
+        Lessons,
+        Search,
+        StopAdd,
+        StopRemove,
+        StopList,
+        Conjugate,
+        TagAdd,
+        TagRemove,
+        TagList,
+        Migrate,
+        BackupList,
+        BackupRestore(usize),
+        ProfileList,
+        ProfileCreate(String),
+        ProfileCopy(String, String),
+        ProfileDelete(String),
     }
 
 
     pub struct SSData {
         pub flags: Option<Flags>,
         pub file_args: Option<Vec<String>>,
+        pub ingest_mode: IngestMode,
+        pub dict_file: Option<String>,
+        pub data_file: Option<String>,
+        pub profile: Option<String>,
+        pub shared: bool,
+        pub overwrite_glosses: bool,
+        pub filter: WordFilter,
+        pub deinflect: bool,
+        pub romaji: romaji::System,
+        pub ime: bool,
+        pub reverse: bool,
     }
 
     impl SSData {
+        #[allow(clippy::let_and_return)]
         pub fn new() -> SSData {
             let d = SSData {
                 flags: None,
                 file_args: None,
+                ingest_mode: IngestMode::Delimited,
+                dict_file: None,
+                data_file: None,
+                profile: None,
+                shared: false,
+                overwrite_glosses: false,
+                filter: WordFilter::default(),
+                deinflect: true,
+                romaji: romaji::System::Hepburn,
+                ime: false,
+                reverse: false,
             };
             d
         }
     }
+
+
+    // Narrows down which words the printing and testing commands use.
+    #[derive(Default)]
+    pub struct WordFilter {
+        pub lesson: Option<LessonDate>,
+        // Words need every one of these.
+        pub tags: Vec<String>,
+    }
+
+    impl WordFilter {
+        pub fn matches(&self, w: &JPWord) -> bool {
+            let in_lesson = match &self.lesson {
+                Some(l) => w.lessons.iter().any(|x| x.same_day(l)),
+                None => true,
+            };
+            in_lesson && self.tags.iter().all(|t| w.tags.contains(t))
+        }
+    }
 }
 
 
 // Mod contains all processes, as well as the structs neseccary for processing.
 mod processes {
-    use std::{collections, path, fs, env, process, io};
+    use std::{collections, path, fs, process, io};
     use std:: error::Error;
-    use std::io::{prelude::*, stdin};
     use rand::Rng;
-    use crate::structures::{JPWord, JapaneseWordParser, WordType};
-
-
-    // Where data is saved.
-    //
-    // I should probably change this, but, eh....
-    const J_SAVE_FILE: &str = "data/word_list.json";
+    use crate::structures::{IngestMode, JPWord, JapaneseWordParser, JapaneseWordType, LessonDate, Occurrence, Sense, Sentence, VerbClass, WordType};
+    use crate::segmenter::Segmenter;
+    use crate::glosses;
+    use crate::ss_data::{SSData, WordFilter};
+    use crate::classify;
+    use crate::backup;
+    use crate::config;
+    use crate::conjugate;
+    use crate::dates;
+    use crate::deinflect;
+    use crate::kana;
+    use crate::lock;
+    use crate::migrate;
+    use crate::profile;
+    use crate::romaji;
+    use crate::store;
 
 
     // This is the main funciton. This will take a file and read the words,
     // combine with the old list, remove duplicates, sort and then store the
     // words!
-    pub fn process_files() -> Result<(), Box<dyn Error>> {
-        // process the args
-        let local_args: Vec<String> = env::args().collect();
-        let arg_count = local_args.len();
-
-        // Remember, this is all the args passed into the program.
-        if arg_count == 1 {
+    pub fn process_files(data: &SSData) -> Result<(), Box<dyn Error>> {
+        // Remember, these are only the args that weren't flags.
+        let files = data.file_args.as_deref().unwrap_or_default();
+        if files.is_empty() {
             eprintln!("No file given");
             process::exit(1);
         }
 
         // make sure the word_list.json file is there
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
+        // Get words that were already in the list.
+        let mut store = store::open(true);
+
+        // d_f_words means deserialized_file_words.
+        let mut d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+
+        // What the words looked like before, so only the ones that change
+        // get saved.
+        let before: collections::HashMap<String, JPWord> = d_f_words.iter()
+            .map(|w| (w.key(), w.clone()))
+            .collect();
+
+        // The segmenter needs to know what words exist before it can split
+        // anything, so build its lexicon out of the list and the dictionary.
+        let mut segmenter = Segmenter::new();
+        if data.ingest_mode == IngestMode::Segmented {
+            for w in &d_f_words {
+                segmenter.add_word(&w.word);
+            }
+            if let Some(dict) = &data.dict_file {
+                let count = segmenter.load_dictionary(dict)?;
+                println!("Loaded {} words from {}", count, dict);
+            }
         }
 
+        // Segmented text is still split on spaces and punctuation first, the
+        // segmenter only has to deal with what's between them.
+        let parser_mode = match data.ingest_mode {
+            IngestMode::Segmented => IngestMode::Delimited,
+            m => m,
+        };
+
         // read the file from the args
         // Process chars, one-by-one, from multiple files.
-        let mut p = JapaneseWordParser::new();
         let mut words: Vec<JPWord> = Vec::new();
 
-        let mut c = false;
-        for a in local_args {
-            if c == false {
-                c = true;
-            } else {
-                let arg_file = fs::read_to_string(&a)
-                    .expect("Could not read file");
-                for ch in arg_file.chars() {
-                    match p.add_to_word(ch) {
-                        None => (),
-                        Some(s) => {
-                            words.push(JPWord::simple_new(s));
-                            p.word.clear();
+        // Anything on the stop list is skipped.
+        let stop_keys: collections::HashSet<String> = load_stop_list().iter()
+            .map(|w| kana::identity_key(w))
+            .collect();
+        let mut suppressed = 0;
+
+        // Japanese words and the english written next to them. This, and the
+        // other maps below, use the word's key (see kana::identity_key).
+        let mut glosses: collections::HashMap<String, String> = collections::HashMap::new();
+
+        // Example sentences, and which sentences each word showed up in.
+        let mut sentences = store.sentences();
+        let sentences_before = sentences.clone();
+        let mut links: collections::HashMap<String, Vec<u32>> = collections::HashMap::new();
+
+        // Everywhere each word was seen.
+        let mut seen_at: collections::HashMap<String, Vec<Occurrence>> = collections::HashMap::new();
+
+        // Readings written in brackets after a word, like コーヒー（こーひー）.
+        let mut alternates: collections::HashMap<String, Vec<String>> = collections::HashMap::new();
+
+        // Every word we know about (by key), which helps deinflection pick
+        // the right dictionary form, and the inflected forms that were seen
+        // for each dictionary form.
+        let mut lexicon: collections::HashSet<String> = collections::HashSet::new();
+        for w in &d_f_words {
+            lexicon.insert(w.key());
+        }
+        let mut forms: collections::HashMap<String, Vec<String>> = collections::HashMap::new();
+
+        // Which lesson each word was in. A lesson starts at a date header,
+        // like 6がつ　２１にち　すいようび, and goes until the next one.
+        let mut in_lessons: collections::HashMap<String, Vec<LessonDate>> = collections::HashMap::new();
+
+        for a in files {
+            let arg_file = fs::read_to_string(a)
+                .expect("Could not read file");
+            let source = path::Path::new(a)
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or(a.clone());
+            let lines: Vec<&str> = arg_file.lines().collect();
+            let mut lesson: Option<LessonDate> = None;
+
+            for (i, line) in lines.iter().enumerate() {
+                if let Some(d) = dates::parse_header(line) {
+                    println!("Found the lesson from {} in {}", d, source);
+                    lesson = Some(d);
+                }
+
+                for (jp, en) in glosses::extract(line) {
+                    glosses.entry(kana::identity_key(&jp)).or_insert(en);
+                }
+
+                let sentence_id = Sentence::from_lines(&lines[i..])
+                    .map(|(text, translation)| add_sentence(&mut sentences, text, translation, &source));
+
+                // Take the readings out of the line before it's split up, so
+                // they don't end up as words of their own.
+                let (line_no_readings, readings) = split_readings(line);
+                for (w, r) in readings {
+                    let a = alternates.entry(kana::identity_key(&w)).or_default();
+                    if !a.contains(&r) {
+                        a.push(r);
+                    }
+                }
+
+                let mut line_words: Vec<String> = Vec::new();
+                for t in parse_line(&line_no_readings, parser_mode) {
+                    if data.ingest_mode == IngestMode::Segmented {
+                        line_words.append(&mut segmenter.segment(&t));
+                    } else {
+                        line_words.push(t);
+                    }
+                }
+
+                for w in line_words {
+                    if stop_keys.contains(&kana::identity_key(&w)) {
+                        suppressed += 1;
+                        continue;
+                    }
+
+                    // Inflected words get saved under their dictionary form,
+                    // so たべました goes with たべる.
+                    let w = match deinflect::deinflect(&w, &lexicon) {
+                        Some(lemma) if data.deinflect && !stop_keys.contains(&kana::identity_key(&lemma)) => {
+                            let key = kana::identity_key(&lemma);
+                            lexicon.insert(key.clone());
+                            let f = forms.entry(key).or_default();
+                            if !f.contains(&w) {
+                                f.push(w);
+                            }
+                            lemma
+                        }
+                        _ => w,
+                    };
+                    lexicon.insert(kana::identity_key(&w));
+
+                    if let Some(id) = sentence_id {
+                        let ids = links.entry(kana::identity_key(&w)).or_default();
+                        if !ids.contains(&id) {
+                            ids.push(id);
                         }
                     }
+                    seen_at.entry(kana::identity_key(&w)).or_default().push(Occurrence {
+                        file: source.clone(),
+                        line: i + 1,
+                        context: line.trim().to_string(),
+                    });
+                    if let Some(d) = &lesson {
+                        let dates = in_lessons.entry(kana::identity_key(&w)).or_default();
+                        if !dates.contains(d) {
+                            dates.push(d.clone());
+                        }
+                    }
+                    words.push(JPWord::simple_new(w));
                 }
             }
         }
 
-        words.sort();
+        // Add the words together, remove duplicates and sort. Words are the
+        // same if they have the same key (see kana::identity_key), so 
+        // コーヒー and こーひー end up as one word. The first spelling wins, and
+        // the other is kept as an alternate.
+        d_f_words.append(&mut words);
+
+        let mut seen: collections::HashMap<String, usize> = collections::HashMap::new();
+        let mut merged: Vec<JPWord> = Vec::new();
+        for w in d_f_words {
+            match seen.get(&w.key()) {
+                Some(&i) => merged[i].absorb(w),
+                None => {
+                    seen.insert(w.key(), merged.len());
+                    merged.push(w);
+                }
+            }
+        }
+        let mut d_f_words = merged;
 
-        // This removes duplicates.
-        let mut seen: collections::HashSet<JPWord> = collections::HashSet::new();
-        words.retain(|word|
-                     seen.insert(word.clone()));
+        // Sort.
+        d_f_words.sort();
 
-        // Get words that were already in the file.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-                            
-        // d_f_words means deserialized_file_words.
-        let mut d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
-            Vec::new()
+        // Fill in the definitions that were found. Words that already have
+        // one are left alone, unless the user asked for them to be replaced.
+        let mut defined = 0;
+        for w in &mut d_f_words {
+            if let Some(f) = forms.get(&w.key()) {
+                for form in f {
+                    if *form != w.word && !w.forms.contains(form) {
+                        w.forms.push(form.clone());
+                    }
+                }
+            }
+
+            // A definition found next to an inflected form counts too.
+            let gloss = glosses.get(&w.key())
+                .or_else(|| w.forms.iter().find_map(|f| glosses.get(&kana::identity_key(f))));
+            if let Some(g) = gloss {
+                if w.senses.is_empty() {
+                    w.senses.push(Sense::new(g));
+                    defined += 1;
+                } else if data.overwrite_glosses && w.senses[0].gloss != g.trim() {
+                    w.senses[0].gloss = g.trim().to_string();
+                    defined += 1;
+                }
+            }
+        }
+        if defined > 0 {
+            println!("Added definitions to {} words", defined);
+        }
+        if suppressed > 0 {
+            println!("Skipped {} words on the stop list", suppressed);
+        }
+
+        // Link words to the example sentences they showed up in, and 
+        // remember where they were seen.
+        for w in &mut d_f_words {
+            if let Some(ids) = links.get(&w.key()) {
+                for id in ids {
+                    if !w.sentences.contains(id) {
+                        w.sentences.push(*id);
+                    }
+                }
+            }
+            if let Some(occurrences) = seen_at.get(&w.key()) {
+                for o in occurrences {
+                    w.add_occurrence(o.clone());
+                }
+            }
+            if let Some(readings) = alternates.get(&w.key()) {
+                for r in readings {
+                    if *r != w.word && !w.alternates.contains(r) {
+                        w.alternates.push(r.clone());
+                    }
+                }
+            }
+
+            // A word with kanji that was also seen written in kana is read
+            // that way.
+            if w.reading.is_none() && w.has_kanji() {
+                let kana = w.alternates.iter()
+                    .find(|a| a.chars().all(|c| matches!(JapaneseWordType::of(c), JapaneseWordType::Hiragana | JapaneseWordType::Katakana)))
+                    .cloned();
+                if let Some(r) = kana {
+                    w.set_reading(&r);
+                }
+            }
+            w.fill_reading();
+            if seen_at.contains_key(&w.key()) {
+                for t in &data.filter.tags {
+                    w.add_tag(t);
+                }
+            }
+            if let Some(dates) = in_lessons.get(&w.key()) {
+                for d in dates {
+                    if !w.lessons.contains(d) {
+                        w.lessons.push(d.clone());
+                    }
+                }
+            }
+        }
+
+        // Guess the type of any word that doesn't have one yet. The user
+        // gets to confirm it with -s.
+        for w in &mut d_f_words {
+            if w.word_type.is_none() {
+                w.suggested_type = classify::suggest(&w.word, &w.forms);
+            }
+        }
+
+        for s in sentences {
+            if !sentences_before.contains(&s) {
+                store.upsert_sentence(s);
+            }
+        }
+
+        // save the new list of words!
+        for w in d_f_words {
+            if before.get(&w.key()) != Some(&w) {
+                store.upsert(w);
+            }
+        }
+        store.save();
+        Ok(())
+    }
+
+
+    // Adds a sentence to the list and returns its id. If the sentence is
+    // already there, its id is returned instead, and the translation is
+    // filled in if it was missing.
+    fn add_sentence(sentences: &mut Vec<Sentence>, text: String, translation: Option<String>, source: &str) -> u32 {
+        if let Some(s) = sentences.iter_mut().find(|s| s.text == text) {
+            if s.translation.is_none() {
+                s.translation = translation;
+            }
+            return s.id
+        }
+
+        let id = sentences.iter().map(|s| s.id + 1).max().unwrap_or(0);
+        sentences.push(Sentence {
+            id,
+            text,
+            translation,
+            source: source.to_string(),
+        });
+        id
+    }
+
+
+    // Print out the list of word types, and ask the user to pick one. 0 or
+    // anything that isn't on the list skips it, and returns None. If there's
+    // a default, just hitting enter picks it.
+    fn prompt_word_type(default: Option<WordType>) -> Option<WordType> {
+        let mut u_prompt = String::new();
+        println!("What type of word is it?");
+        println!("Please enter the corresponding number, or 0 to skip");
+        for (i, t) in WordType::ALL.iter().enumerate() {
+            println!("{:<2} -> {}", i + 1, t);
+        }
+        if let Some(t) = default {
+            println!("Hit enter to use {}.", t);
+        }
+        io::stdin()
+            .read_line(&mut u_prompt)
+            .expect("Could not read input from user");
+
+        if u_prompt.trim().is_empty() && default.is_some() {
+            return default
+        }
+
+        let u_prompt: usize = match u_prompt.trim().parse() {
+            Ok(num) => num,
+            Err(_) => {
+                eprintln!("That's not a number. Skipping to next part");
+                return None
+            }
+        };
+
+        match u_prompt {
+            0 => None,
+            n if n <= WordType::ALL.len() => Some(WordType::ALL[n - 1]),
+            _ => {
+                eprintln!("Invalid number. Skipping to next part");
+                None
+            }
+        }
+    }
+
+
+    // Reads a line of japanese from the user. With --ime, romaji gets turned
+    // into kana (see romaji::convert_input), and the kana is shown so the user
+    // can see what it was read as.
+    fn read_japanese(ime: bool) -> String {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Could not read input");
+        if !ime {
+            return input
+        }
+
+        let converted = romaji::convert_input(input.trim());
+        if converted != input.trim() {
+            println!("-> {}", converted);
+        }
+        converted
+    }
+
+
+    // Finds a word by how it's written or how it's read. Romaji works too,
+    // so taberu finds 食べる as long as its reading is filled in.
+    fn find_word(words: &[JPWord], input: &str) -> Option<usize> {
+        let input = input.trim();
+        let key = if romaji::is_romaji(input) {
+            kana::identity_key(&romaji::to_kana(input, false))
         } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
+            kana::identity_key(input)
         };
 
-        // Add the words together, remove duplicates and sort.
-        d_f_words.append(&mut words);
+        words.iter().position(|w| w.key() == key)
+            .or_else(|| words.iter().position(|w| w.reading.as_deref().map(kana::identity_key) == Some(key.clone())))
+    }
 
-        // Sort.
-        let mut seen = collections::HashSet::new();
-        d_f_words.retain(|word|
-                          seen.insert(word.word.clone()));
 
-        d_f_words.sort();
+    // Ask the user how a word with kanji is read. Hitting enter keeps the
+    // reading it already has.
+    fn prompt_reading(w: &mut JPWord, ime: bool) {
+        if !w.has_kanji() {
+            return
+        }
+        match &w.reading {
+            Some(r) => println!("How is {} read? It's read {} now, hit enter to keep that.", w.word, r),
+            None => println!("How is {} read? Please type it in kana, or hit enter to skip.", w.word),
+        }
+        let u_prompt = read_japanese(ime);
+        if !u_prompt.trim().is_empty() {
+            w.set_reading(&u_prompt);
+        }
+    }
 
-        // save the new list of words to the file!
-        let stringified =  serde_json::to_string(&d_f_words).expect("Could not parse into JSON before writing");
-        fs::write(J_SAVE_FILE, &stringified)?;
-        Ok(())
+
+    // Ask the user for a meaning, and an optional note to go with it. If
+    // ask_type is set, they're asked what type of word it is in this sense
+    // too. Returns None if nothing was entered.
+    fn prompt_sense(ask_type: bool, word_type: Option<WordType>) -> Option<Sense> {
+        println!("Alright, what does this word mean?");
+        println!("Please, do not hit enter or enter a newline char in your response");
+        let mut u_prompt = String::new();
+        io::stdin()
+            .read_line(&mut u_prompt)
+            .expect("Could not read input from user");
+        if u_prompt.trim().is_empty() {
+            return None
+        }
+        let mut sense = Sense::new(&u_prompt);
+
+        if ask_type {
+            sense.word_type = prompt_word_type(word_type).filter(|t| Some(*t) != word_type);
+        }
+
+        println!("Any notes on this meaning? Hit enter to skip.");
+        let mut u_prompt = String::new();
+        io::stdin()
+            .read_line(&mut u_prompt)
+            .expect("Could not read input from user");
+        if !u_prompt.trim().is_empty() {
+            sense.note = Some(u_prompt.trim().to_string());
+        }
+
+        Some(sense)
+    }
+
+
+    // Read the stop list. It's fine if there isn't one yet.
+    fn load_stop_list() -> Vec<String> {
+        match fs::read_to_string(config::stop_file()) {
+            Ok(s) if !s.trim().is_empty() => {
+                serde_json::from_str(&s).expect("Could not parse json from stop list")
+            }
+            _ => Vec::new(),
+        }
+    }
+
+
+    fn save_stop_list(stop_list: &Vec<String>) {
+        let _dir = fs::create_dir_all(config::data_dir());
+        let stringified = serde_json::to_string(stop_list).expect("Could not parse into JSON before writing");
+        backup::write_atomic(&config::stop_file(), &stringified)
+            .expect("Could not write to file");
+    }
+
+
+    // Finds words with their reading in brackets after them, like
+    // コーヒー（こーひー） or テレビ(てれび). Returns the line without the 
+    // readings, and each (word, reading) that was found. Brackets with 
+    // anything other than kana in them, like は（wa), are left alone.
+    fn split_readings(line: &str) -> (String, Vec<(String, String)>) {
+        let mut out = String::new();
+        let mut found: Vec<(String, String)> = Vec::new();
+        let mut rest = line;
+
+        while let Some(open) = rest.find(['\u{FF08}', '(']) {
+            let (before, after) = rest.split_at(open);
+            out.push_str(before);
+
+            let open_len = after.chars().next().map_or(1, char::len_utf8);
+            let inside = &after[open_len..];
+            let close = inside.find(['\u{FF09}', ')']);
+
+            // The word is the japanese right in front of the bracket.
+            let word: String = out.chars().rev()
+                .take_while(|c| JapaneseWordType::of(*c) != JapaneseWordType::Neither)
+                .collect::<Vec<char>>()
+                .into_iter()
+                .rev()
+                .collect();
+
+            match close {
+                Some(c) if !word.is_empty() && is_reading(&inside[..c]) => {
+                    found.push((word, inside[..c].to_string()));
+                    let close_len = inside[c..].chars().next().map_or(1, char::len_utf8);
+                    rest = &inside[c + close_len..];
+                    // Keep the words on either side apart.
+                    out.push(' ');
+                }
+                _ => {
+                    out.push_str(&after[..open_len]);
+                    rest = inside;
+                }
+            }
+        }
+        out.push_str(rest);
+        (out, found)
+    }
+
+
+    fn is_reading(s: &str) -> bool {
+        !s.is_empty() && s.chars().all(|c| matches!(
+            JapaneseWordType::of(c),
+            JapaneseWordType::Hiragana | JapaneseWordType::Katakana,
+        ))
+    }
+
+
+    // Splits a single line up into words.
+    fn parse_line(line: &str, mode: IngestMode) -> Vec<String> {
+        let mut p = JapaneseWordParser::new(mode);
+        let mut words: Vec<String> = Vec::new();
+        for ch in line.chars() {
+            if let Some(s) = p.add_to_word(ch) {
+                words.push(s);
+            }
+        }
+
+        // Don't lose a word sitting at the very end of the line.
+        if let Some(s) = p.finish() {
+            words.push(s);
+        }
+        words
     }
 
 
     // Print a help menu.
+    #[allow(clippy::println_empty_string)]
     pub fn print_help() {
         println!("Welcome to sear_stone! This program pulls Japanese words out");
         println!("of files to aid in study.");
@@ -195,8 +862,38 @@ mod processes {
         println!("Here is how to use it:");
         println!("1) If no flags are passed, all args are assumed to be text ");
         println!("files that contains Japanese words and will be proccessed.");
-        println!("These words will be added to a file called data/words_list.json");
-        println!("that is located in the directory where sear_stone is ran.");
+        println!("These words will be added to the word list, which is kept in");
+        println!("{}", config::word_file().display());
+        println!("");
+        println!("   To keep it somewhere else, use --data <file>, set SEAR_STONE_DATA,");
+        println!("or put {{\"data\": \"<file>\"}} in the config file at");
+        println!("~/.config/sear_stone/config.json. The stop list is kept");
+        println!("next to it.");
+        println!("");
+        println!("   Big lists can be kept in a SQLite database instead, so saving");
+        println!("doesn't rewrite the whole list. Put {{\"store\": \"sqlite\"}} in the");
+        println!("config file to use one. It's called word_list.sqlite by default. The");
+        println!("first time it's used, the words in word_list.json are copied into it.");
+        println!("A word list ending in .json or .sqlite is always opened as that kind,");
+        println!("whatever the config file says.");
+        println!("");
+        println!("   By default, words are split wherever the file has a space or");
+        println!("punctuation. Add -m script or --mode script to split words wherever");
+        println!("the text changes between hiragana, katakana and kanji instead. This");
+        println!("is better for text that doesn't have spaces between words.");
+        println!("");
+        println!("   -m dictionary also works for text without spaces. It splits the");
+        println!("text into words that are already in your list. Add --dict <file>");
+        println!("to also use a dictionary file, one word per line.");
+        println!("");
+        println!("   Lines like あかいろ　Red or Monday   げつようび are used to fill in");
+        println!("definitions for words that don't have one. Add --overwrite-glosses");
+        println!("to replace definitions that were already there. Readings written in");
+        println!("brackets, like コーヒー（こーひー）, are saved with the word in front of them.");
+        println!("");
+        println!("   Verbs and adjectives are saved in their dictionary form, so たべます,");
+        println!("たべません and たべました all go with たべる. Add --no-deinflect to save them");
+        println!("as they are.");
         println!("");
         println!("2) -h or --help will print this menu.");
         println!("");
@@ -207,62 +904,104 @@ mod processes {
         println!("words_list.txt");
         println!("");
         println!("5) -s or --set-meaning will guide you through adding a definition");
-        println!("and type to a word that is already added.");
+        println!("and type to a word that is already added. If the type was guessed");
+        println!("when the word was added, hitting enter accepts the guess. Words can");
+        println!("have more than one meaning, run it again to add another one or to");
+        println!("replace one that's there. Words with kanji also get asked how they're");
+        println!("read, words in kana are read the way they're written.");
         println!("");
         println!("6) -aw or --add-word will guide you through adding a word to the list.");
         println!("");
         println!("7) -rw or --remove-word will remove the word you choose.");
         println!("");
         println!("8) -t or --test will present a random word from the list. After");
-        println!("hitting enter, it will display the definition, if there is one. ");
+        println!("hitting enter, it will display the definition, if there is one, ");
+        println!("and a couple of example sentences (the lines starting with ●) that");
+        println!("the word was used in. Sentences are kept in the word list too.");
+        println!("Add --reverse to be shown the meaning and type the japanese instead.");
+        println!("");
+        println!("9) -r or --review will check which words are due for review again.");
+        println!("");
+        println!("10) -w or --where will show every file and line a word was found on.");
+        println!("");
+        println!("11) -l or --lessons will list the lessons words came from. A lesson");
+        println!("starts at a date line, like 6がつ　２１にち　すいようび. Add --lesson 6/21");
+        println!("to -pa, -pr or -t to only use the words from that lesson.");
+        println!("");
+        println!("12) -f or --search will print every word that has what you type in it.");
+        println!("");
+        println!("13) -sa or --stop-add will add words to the stop list. Words on the");
+        println!("stop list, like を or です, are skipped when files are processed.");
+        println!("-sr or --stop-remove takes words off of it, and -sl or --stop-list");
+        println!("prints it. It's kept in stop_list.json.");
+        println!("");
+        println!("14) -c or --conjugate will pick a random verb from the list and ask");
+        println!("you to conjugate it, like たべる into the polite past (たべました).");
+        println!("");
+        println!("15) -ta or --tag-add will tag words, like colors or family. -tr or");
+        println!("--tag-remove takes a tag off, and -tl or --tag-list prints every tag.");
+        println!("Add --tag colors to -pa, -pr or -t to only use the words tagged colors,");
+        println!("or to processing files to tag every word found in them.");
+        println!("");
+        println!("16) -mg or --migrate will upgrade word_list.json if it was saved");
+        println!("by an older version of sear_stone, and show what changed. This happens");
+        println!("on its own whenever the list is loaded too, -mg just shows you.");
+        println!("");
+        println!("17) backup list will show the backups of the word list. One is made");
+        println!("every time the list is saved, and the newest 10 are kept (set \"backups\"");
+        println!("in the config file to keep more). backup restore 2 puts backup 2 back.");
+        println!("Only the backups of the kind of list being used (JSON or SQLite) are shown.");
+        println!("");
+        println!("18) Profiles let more than one person study on the same computer.");
+        println!("profile create alice makes one, and adding --profile alice to any");
+        println!("other command uses alice's word list instead of the main one. Add");
+        println!("--shared when creating it to use the main word list, but keep alice's");
+        println!("own review progress. profile list, profile copy alice bob and profile");
+        println!("delete alice do what they say.");
+        println!("");
+        println!("   More than one sear_stone can use the list at a time, like -t in one");
+        println!("terminal and processing files in another. Each one only saves the words it");
+        println!("changed, so they don't undo each other.");
+        println!("");
+        println!("   Words are matched without caring about hiragana vs katakana, or half");
+        println!("vs full width, so コーヒー, こーひー and ｺｰﾋｰ are all the same word. This");
+        println!("goes for -s, -aw, -rw, -w and when files are processed too.");
+        println!("");
+        println!("   -s and -rw also take romaji, like taberu or koohii. Both Hepburn");
+        println!("(shi, tsu, ja) and Kunrei (si, tu, zya) spellings work. -t shows the");
+        println!("reading in Hepburn, add --romaji kunrei to see it in Kunrei instead.");
+        println!("");
+        println!("   No japanese keyboard? Add --ime to -s, -aw, -t --reverse or -c and");
+        println!("type romaji, it gets turned into hiragana when you hit enter. Start a");
+        println!("word with a capital letter to get katakana, and use - for a long");
+        println!("vowel (Ko-hi- -> コーヒー).");
         println!("");
     }
 
     
     // Print all the words in the word_list.json file.
-    pub fn print_all() {
+    pub fn print_all(filter: &WordFilter) {
         // Check if file exists.
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        // Get the string.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-
-        // Deserialize the string.
-        let d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
-            Vec::new()
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(filter);
 
         // Print it out!
-        for w in d_f_words {
+        for w in &d_f_words {
             println!("{}", w.word);
         }
     }
 
 
     // Print 10 random words.
-    pub fn print_random() {
+    pub fn print_random(filter: &WordFilter) {
         // Make sure the file is there.
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        // Read file to string.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(filter);
 
-        // Deserialzie the string.
-        let d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
-            Vec::new()
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        if d_f_words.is_empty() {
+            eprintln!("There aren't any words to print!");
+            process::exit(1);
+        }
 
         // Get the things I need to print out random words.
         let mut rng = rand::thread_rng();
@@ -271,8 +1010,9 @@ mod processes {
         // Create vec to hold the random numbers.
         let mut ran_num: Vec<usize> = Vec::new();
 
-        // Get 10 random numbers within range.
-        while ran_num.len() < 10 {
+        // Get 10 random numbers within range, or every word if there
+        // aren't 10.
+        while ran_num.len() < d_f_words.len().min(10) {
             let t = rng.gen_range(0..=vec_len);
             if !ran_num.contains(&t) {
                 ran_num.push(t);
@@ -287,29 +1027,18 @@ mod processes {
 
 
     // Set a type and definition for a word that is part of the list
-    pub fn set_meaning() -> () {
+    pub fn set_meaning(data: &SSData) {
         // Read current list of words, in word_list.json
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        // Get the string from file.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-
-        // Deserialize the string.
-        let mut d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
+        let mut store = store::open(true);
+        let mut d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+        if d_f_words.is_empty() {
             eprintln!("File is empty! Can't define any words...");
             process::exit(1);
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        }
 
         // prompt user for the word they would like to define
-        let mut u_prompt: String = String::new();
         println!("Please enter the word you'd like to define: ");
-        io::stdin().read_line(&mut u_prompt).expect("Could not read input");
+        let u_prompt = read_japanese(data.ime);
 
         // Check for nothing.
         if u_prompt.trim().is_empty() {
@@ -318,7 +1047,7 @@ mod processes {
         }
 
         // find that word
-        let index = d_f_words.iter().position(|item| item.word == u_prompt.trim());
+        let index = find_word(&d_f_words, &u_prompt);
 
         // if I can find the word, grab it's index
         let index = match index {
@@ -335,182 +1064,104 @@ mod processes {
         // word type, this will list the word types and ask the user to 
         // enter a number that corresponds with that type
         // definition, user will type out the definition
-        let mut u_prompt = String::new();
-        println!("What type of word is it?");
-        println!("Please enter the corresponding number, or 0 to skip");
-        println!("1  -> Noun");
-        println!("2  -> Pronoun");
-        println!("3  -> Verb");
-        println!("4  -> Adjective");
-        println!("5  -> Adverb");
-        println!("6  -> Preposition");
-        println!("7  -> Conjuntion");
-        println!("8  -> Interjunction");
-        println!("9  -> Article");
-        println!("10 -> Quantifier");
-        println!("11 -> Auxiliary");
-        println!("12 -> Phrase");
-        io::stdin()
-            .read_line(&mut u_prompt)
-            .expect("Could not read input from user");
-        let u_prompt: u32 = match u_prompt.trim().parse() {
-            Ok(num) => num,
-            Err(e) => panic!("Could not parse number: {}", e),
-        };
+        prompt_reading(&mut d_f_words[index], data.ime);
+        // Enter keeps the type it already has, and the guess is only used if
+        // it doesn't have one yet. Skipping leaves both alone.
+        let word = &mut d_f_words[index];
+        if let Some(t) = prompt_word_type(word.word_type.or(word.suggested_type)) {
+            word.word_type = Some(t);
+            word.suggested_type = None;
+        }
 
-        // Set the word type.
-        d_f_words[index].word_type = match u_prompt {
-            0 => None,
-            1 => Some(WordType::Noun),
-            2 => Some(WordType:: Pronoun),
-            3 => Some(WordType::Verb),
-            4 => Some(WordType::Adjective),
-            5 => Some(WordType::Adverb),
-            6 => Some(WordType::Preposition),
-            7 => Some(WordType::Conjunction),
-            8 => Some(WordType::Interjection),
-            9 => Some(WordType::Article),
-            10 => Some(WordType::Quantifier),
-            11 => Some(WordType::Auxiliary),
-            _ => {
-                eprintln!("Invalid number. Skipping to next part");
-                None
+        // If it already has meanings, the user can replace one of them or
+        // add another.
+        let mut replace: Option<usize> = None;
+        if !d_f_words[index].senses.is_empty() {
+            println!("It already means:");
+            for (i, sense) in d_f_words[index].senses.iter().enumerate() {
+                println!("{:<2} -> {}", i + 1, sense);
             }
-        };
+            println!("Enter a number to replace that meaning, or hit enter to add a new one");
+            let mut u_prompt = String::new();
+            io::stdin()
+                .read_line(&mut u_prompt)
+                .expect("Could not read input from user");
+            replace = match u_prompt.trim().parse::<usize>() {
+                Ok(n) if n >= 1 && n <= d_f_words[index].senses.len() => Some(n - 1),
+                _ => None,
+            };
+        }
 
-        // Set definition.
-        println!("Alright, what does this word mean?");
-        println!("Please, do not hit enter or enter a newline char in your response");
-        let mut u_prompt = String::new();
-        io::stdin()
-            .read_line(&mut u_prompt)
-            .expect("Could not read input from user");
-        d_f_words[index].definition = Some(u_prompt);
+        // Set definition. The type only gets asked for again when the word
+        // is going to have more than one meaning.
+        let word_type = d_f_words[index].word_type;
+        let ask_type = d_f_words[index].senses.len() + usize::from(replace.is_none()) > 1;
+        match (prompt_sense(ask_type, word_type), replace) {
+            (None, _) => eprintln!("Nothing entered! Leaving the meanings alone"),
+            (Some(sense), Some(i)) => d_f_words[index].senses[i] = sense,
+            (Some(sense), None) => {
+                if !d_f_words[index].add_sense(sense) {
+                    println!("It already has that meaning!");
+                }
+            }
+        }
 
-        // save the new vec to the file
-        let stringified = serde_json::to_string(&d_f_words).expect("Could not parse into JSON before writing");
-        fs::write(J_SAVE_FILE, &stringified)
-            .expect("Could not write to file");
+        // save the word
+        store.upsert(d_f_words.swap_remove(index));
+        store.save();
     }
 
 
     // Add a word to the file.
-    pub fn add_word() -> () {
+    pub fn add_word(data: &SSData) {
         // Make sure it's there.
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        // Read file to string.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-
-        // Deserialize string.
-        let mut d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
+        let mut store = store::open(true);
+        if store.query(&WordFilter::default()).is_empty() {
             eprintln!("File is empty! Can't define any words...");
             process::exit(1);
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        }
 
         // Get the word to add to the list.
-        let mut input = String::new();
         println!("What word would you like to add?");
-        io::stdin().read_line(&mut input).expect("Could not read input");
+        let input = read_japanese(data.ime);
 
         // Check if word is in list
-        for w in &d_f_words {
-            if w.word == String::from(input.trim()) {
-                eprintln!("Sorry, that word is already in the list!!!");
-                process::exit(0);
-            }
+        let key = kana::identity_key(input.trim());
+        if let Some(w) = store.get(&key) {
+            eprintln!("Sorry, that word is already in the list!!! ({})", w.word);
+            process::exit(0);
         }
 
         // Add word.
-        d_f_words.push(JPWord::simple_new(String::from(input.trim())));
-        let word_index = d_f_words.len() - 1;
+        let mut word = JPWord::simple_new(String::from(input.trim()));
+        word.fill_reading();
+        prompt_reading(&mut word, data.ime);
 
         // Prompt user for the type.
-        let mut u_prompt = String::new();
-        println!("What type of word is it?");
-        println!("Please enter the corresponding number, or 0 to skip");
-        println!("1  -> Noun");
-        println!("2  -> Pronoun");
-        println!("3  -> Verb");
-        println!("4  -> Adjective");
-        println!("5  -> Adverb");
-        println!("6  -> Preposition");
-        println!("7  -> Conjuntion");
-        println!("8  -> Interjunction");
-        println!("9  -> Article");
-        println!("10 -> Quantifier");
-        println!("11 -> Auxiliary");
-        println!("12 -> Phrase");
-        io::stdin()
-            .read_line(&mut u_prompt)
-            .expect("Could not read input from user");
-        let u_prompt: u32 = match u_prompt.trim().parse() {
-            Ok(num) => num,
-            Err(e) => panic!("Could not parse number: {}", e),
-        };
-
-        d_f_words[word_index].word_type = match u_prompt {
-            0 => None,
-            1 => Some(WordType::Noun),
-            2 => Some(WordType:: Pronoun),
-            3 => Some(WordType::Verb),
-            4 => Some(WordType::Adjective),
-            5 => Some(WordType::Adverb),
-            6 => Some(WordType::Preposition),
-            7 => Some(WordType::Conjunction),
-            8 => Some(WordType::Interjection),
-            9 => Some(WordType::Article),
-            10 => Some(WordType::Quantifier),
-            11 => Some(WordType::Auxiliary),
-            _ => {
-                eprintln!("Invalid number. Skipping to next part");
-                None
-            }
-        };
+        let suggested = classify::suggest(&word.word, &[]);
+        word.word_type = prompt_word_type(suggested);
 
         // Prompt for definition.
-        println!("Alright, what does this word mean?");
-        println!("Please, do not hit enter or enter a newline char in your response");
-        let mut u_prompt = String::new();
-        io::stdin()
-            .read_line(&mut u_prompt)
-            .expect("Could not read input from user");
-
-        // Set definition.
-        d_f_words[word_index].definition = Some(u_prompt);
+        if let Some(sense) = prompt_sense(false, word.word_type) {
+            word.add_sense(sense);
+        }
 
-        // save the new vec to the file
-        let stringified = serde_json::to_string(&d_f_words).expect("Could not parse into JSON before writing");
-        fs::write(J_SAVE_FILE, &stringified)
-            .expect("Could not write to file");
+        // save the word
+        store.upsert(word);
+        store.save();
     }
 
 
     // Remove a word from the list.
+    #[allow(clippy::unused_unit)]
     pub fn remove_word() -> () {
         // Check if file is there.
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        // Read file to string.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-
-        // Desirialzie the string.
-        let mut d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
+        let mut store = store::open(true);
+        let mut d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+        if d_f_words.is_empty() {
             eprintln!("File is empty! Can't define any words...");
             process::exit(1);
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        }
 
         // Prompt for word.
         let mut input = String::new();
@@ -518,129 +1169,157 @@ mod processes {
         io::stdin().read_line(&mut input).expect("Could not read input");
 
         // Check if word is in list
-        let mut exist = false;
-        for w in &d_f_words {
-            if w.word == String::from(input.trim()) {
-                exist = true;
-                break;
+        let index = match find_word(&d_f_words, &input) {
+            Some(i) => i,
+            None => {
+                eprintln!("Sorry, that word does not exist in the list");
+                process::exit(0);
             }
-        }
-        if !exist {
-            eprintln!("Sorry, that word does not exist in the list");
-            process::exit(0);
-        }
+        };
 
         // Remove word.
-        d_f_words.retain(|x| x.word != input.trim());
-        println!("Succesfully removed the word!");
+        let removed = d_f_words.remove(index);
+        store.remove(&removed.key());
+        println!("Succesfully removed {}!", removed.word);
 
-        // save the new vec to the file
-        let stringified = serde_json::to_string(&d_f_words).expect("Could not parse into JSON before writing");
-        fs::write(J_SAVE_FILE, &stringified)
-            .expect("Could not write to file");
+        store.save();
     }
 
 
     // Test the users knowledge.
-    pub fn test() -> () {
+    // With --reverse, the user is shown the meaning and has to type the
+    // japanese, which gets checked for them.
+    pub fn test(data: &SSData) {
         // Check if the file is there.
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        // Read file to string.
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-
-        let mut d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
-            eprintln!("File is empty! Can't define any words...");
+        let mut store = store::open(true);
+        let d_f_words: Vec<JPWord> = store.query(&data.filter);
+        if d_f_words.is_empty() {
+            eprintln!("There aren't any words to test!");
             process::exit(1);
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        }
 
         // Get what I need to get randome word.
         let mut testable_words: Vec<JPWord> = Vec::new();
 
         for word in &d_f_words {
-            if word.get_review() {
+            // A reverse test needs a meaning to show.
+            let askable = !data.reverse || !word.senses.is_empty();
+            if word.get_review() && askable {
                 testable_words.push(word.clone());
             }
         }
 
+        if testable_words.is_empty() {
+            println!("There's nothing to review right now!");
+            return
+        }
+
         let mut rng = rand::thread_rng();
         let r_num = rng.gen_range(0..=&testable_words.len()-1);
 
         // Test user!
         println!("Here is a test!");
-        println!("What is {} ?", testable_words[r_num].word);
-
-        let mut buffer = String::new();
-        let _input = io::stdin().read_line(&mut buffer);
-
-        let def_option = &testable_words[r_num]
-            .definition;
-        let def = match def_option {
-            Some(v) => String::from(v.trim()),
-            None => String::from("No Definition"),
-        };
-        println!("\nThe answer is:\n{}", def);
+        let mut answered_right = false;
+        if data.reverse {
+            println!("What is the japanese for: {} ?", testable_words[r_num].glosses());
+            let answer = kana::identity_key(&read_japanese(data.ime));
+            let w = &testable_words[r_num];
+            answered_right = w.key() == answer
+                || w.reading.as_deref().map(kana::identity_key) == Some(answer.clone())
+                || w.alternates.iter().any(|a| kana::identity_key(a) == answer);
+        } else {
+            println!("What is {} ?", testable_words[r_num].word);
+            let mut buffer = String::new();
+            let _input = io::stdin().read_line(&mut buffer);
+        }
 
-        // Get input from user on if they guessed correct.
-        println!("\n\nDid you guess correct!? (y/n)\n");
-        let mut buffer = String::new();
-        let _input = io::stdin()
-            .read_line(&mut buffer)
-            .expect("Could not read input");
+        println!("\nThe answer is:");
+        if data.reverse {
+            println!("{}", testable_words[r_num].word);
+        }
+        if let Some(r) = &testable_words[r_num].reading {
+            let spelled = romaji::to_romaji(r, data.romaji);
+            if *r == testable_words[r_num].word {
+                println!("({})", spelled);
+            } else {
+                println!("{} ({})", r, spelled);
+            }
+        }
+        match testable_words[r_num].senses.as_slice() {
+            [] => println!("No Definition"),
+            [only] => println!("{}", only),
+            senses => {
+                for (i, sense) in senses.iter().enumerate() {
+                    println!("{}. {}", i + 1, sense);
+                }
+            }
+        }
+        if !testable_words[r_num].alternates.is_empty() {
+            println!("Also written as: {}", testable_words[r_num].alternates.join(", "));
+        }
+        if !testable_words[r_num].forms.is_empty() {
+            println!("Seen as: {}", testable_words[r_num].forms.join(", "));
+        }
 
-        let buffer = buffer.to_string();
+        // Show a couple of the sentences the word was used in.
+        let sentences = store.sentences();
+        let examples: Vec<&Sentence> = sentences.iter()
+            .filter(|s| testable_words[r_num].sentences.contains(&s.id))
+            .take(2)
+            .collect();
+        if !examples.is_empty() {
+            println!("\nFor example:");
+            for s in examples {
+                println!("{}", s);
+            }
+        }
 
-        match buffer.as_str().trim() {
-            "N" => println!("Keep trying!!!"),
-            "n" => println!("Keep trying!!!"),
-            "Y" => testable_words[r_num].correct(),
-            "y" => testable_words[r_num].correct(),
-            _ => println!("Keep trying."),
+        // Get input from user on if they guessed correct. A reverse test
+        // already knows.
+        if data.reverse {
+            if answered_right {
+                println!("\nCorrect!!!");
+                testable_words[r_num].correct();
+            } else {
+                println!("\nKeep trying!!!");
+            }
+        } else {
+            println!("\n\nDid you guess correct!? (y/n)\n");
+            let mut buffer = String::new();
+            let _input = io::stdin()
+                .read_line(&mut buffer)
+                .expect("Could not read input");
+
+            let buffer = buffer.to_string();
+
+            match buffer.as_str().trim() {
+                "N" => println!("Keep trying!!!"),
+                "n" => println!("Keep trying!!!"),
+                "Y" => testable_words[r_num].correct(),
+                "y" => testable_words[r_num].correct(),
+                _ => println!("Keep trying."),
+            }
         }
 
         
-        // Add words back to original d_f_words
-        d_f_words.retain(|x| x.word != testable_words[r_num].word);
-        d_f_words.push(testable_words[r_num].clone());
-
-        d_f_words.sort();
-
-        let mut seen = collections::HashSet::new();
-        d_f_words.retain(|word|
-                          seen.insert(word.clone()));
-
-        let stringified = serde_json::to_string(&d_f_words).expect("Could not parse into JSON before writing");
-        fs::write(J_SAVE_FILE, &stringified)
-            .expect("Could not write to file");
+        // Put the word back in the list
+        store.upsert(testable_words.swap_remove(r_num));
+        store.save();
     }
 
 
     pub fn review() {
-        if !path::Path::new(J_SAVE_FILE).exists() {
-            let _dir = fs::create_dir("data");
-            let _file = fs::File::create(J_SAVE_FILE)
-                .expect("Could not create file");
-        }
-
-        let f_words = fs::read_to_string(J_SAVE_FILE).expect("Could not read file");
-
-        let mut d_f_words: Vec<JPWord> = if f_words.trim().is_empty() {
+        let mut store = store::open(true);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+        if d_f_words.is_empty() {
             eprintln!("File is empty! Can't define any words...");
             process::exit(1);
-        } else {
-            serde_json::from_str(&f_words).expect("Could not parse json from file")
-        };
+        }
 
         let now = chrono::Utc::now();
         let mut review_counter = 0;
 
-        for word in &mut d_f_words {
+        for mut word in d_f_words {
             if word.get_review() {
                 review_counter += 1;
                 continue;
@@ -649,305 +1328,4815 @@ mod processes {
             if now - word.get_next_review() > chrono::TimeDelta::zero() {
                 review_counter += 1;
                 word.set_review(true);
-                
+                store.upsert(word);
             }
         }
 
         println!("You have {} words to review!!!", review_counter);
         
-        // save the new vec to the file
-        let stringified = serde_json::to_string(&d_f_words).expect("Could not parse into JSON before writing");
-        fs::write(J_SAVE_FILE, &stringified)
-            .expect("Could not write to file");
+        // save the words that are up for review
+        store.save();
     }
-}
 
 
-// Everything needed to process everything.
-mod structures {
-    use serde::{Serialize, Deserialize};
-    use core::fmt;
-    use std::cmp::Ordering;
-    use chrono::{Duration, Utc};
+    // Show everywhere a word was seen.
+    pub fn show_where() {
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+        if d_f_words.is_empty() {
+            eprintln!("File is empty! Can't look up any words...");
+            process::exit(1);
+        }
 
+        // Prompt for word.
+        let mut input = String::new();
+        println!("What word would you like to look up?");
+        io::stdin().read_line(&mut input).expect("Could not read input");
 
-    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
-    pub enum WordType {
-        Noun,
-        Pronoun,
-        Verb,
-        Adjective,
-        Adverb,
-        Preposition,
-        Conjunction,
-        Interjection,
-        Article,
-        Quantifier,
-        Auxiliary,
-        Phrase
-    }
+        let key = kana::identity_key(input.trim());
+        let word = match d_f_words.iter().find(|w| w.key() == key) {
+            Some(w) => w,
+            None => {
+                eprintln!("Sorry, that word does not exist in the list");
+                process::exit(0);
+            }
+        };
 
-    #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
-    enum TimesReviewed {
-        Zero,
-        One,
-        Two,
-        ThreePlus,
-    }
+        if word.occurrences.is_empty() {
+            println!("{} hasn't been seen in any files yet.", word.word);
+            return
+        }
 
-    impl Default for TimesReviewed {
-        fn default() -> Self {
-            TimesReviewed::Zero
+        println!("{} was seen {} times:", word.word, word.occurrences.len());
+        for o in &word.occurrences {
+            println!("{}", o);
         }
     }
 
 
+    // Print every word that has what the user typed in it. This ignores the
+    // difference between hiragana and katakana, half and full width, etc.
+    pub fn search() {
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
 
-    #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
-    enum ReviewInterval {
-        ThreeHours,
-        OneDay,
-        ThreeDays,
-        OneWeek,
-        TwoWeeks,
-        OneMonth,
-        ThreeMonths,
-        SixMonths,
-        OneYear,
-    }
+        let mut input = String::new();
+        println!("What would you like to search for?");
+        io::stdin().read_line(&mut input).expect("Could not read input");
 
-    impl Default for ReviewInterval {
-        fn default() -> Self {
-            ReviewInterval::ThreeHours
+        let key = kana::identity_key(input.trim());
+        if key.is_empty() {
+            eprintln!("Nothing entered! Exiting program now");
+            process::exit(1);
         }
-    }
 
-    impl ReviewInterval {
-        fn duration(&self) -> chrono::Duration {
-            match self {
-                ReviewInterval::ThreeHours => Duration::hours(3),
-                ReviewInterval::OneDay => Duration::days(1),
-                ReviewInterval::ThreeDays=> Duration::days(3),
-                ReviewInterval::OneWeek=> Duration::weeks(1),
-                ReviewInterval::TwoWeeks=> Duration::weeks(2),
-                ReviewInterval::OneMonth=> Duration::weeks(4),
-                ReviewInterval::ThreeMonths=> Duration::weeks(12),
-                ReviewInterval::SixMonths=> Duration::weeks(24),
-                ReviewInterval::OneYear=> Duration::weeks(52),
+        let mut found = 0;
+        for w in &d_f_words {
+            let matches = w.key().contains(&key)
+                || w.reading.as_deref().is_some_and(|r| kana::identity_key(r).contains(&key))
+                || w.alternates.iter().any(|a| kana::identity_key(a).contains(&key));
+            if matches {
+                found += 1;
+                if w.senses.is_empty() {
+                    println!("{}", w.word);
+                } else {
+                    println!("{}  {}", w.word, w.glosses());
+                }
             }
         }
 
-        fn next_dur(&mut self) -> ReviewInterval {
-            match self {
-                ReviewInterval::ThreeHours => ReviewInterval::OneDay,
-                ReviewInterval::OneDay=> ReviewInterval::ThreeDays,
-                ReviewInterval::ThreeDays=> ReviewInterval::OneWeek,
-                ReviewInterval::OneWeek=> ReviewInterval::TwoWeeks,
-                ReviewInterval::TwoWeeks=> ReviewInterval::OneMonth,
-                ReviewInterval::OneMonth=> ReviewInterval::ThreeMonths,
-                ReviewInterval::ThreeMonths=> ReviewInterval::SixMonths,
-                ReviewInterval::SixMonths=> ReviewInterval::OneYear,
-                ReviewInterval::OneYear=> ReviewInterval::OneYear,
-            }
+        if found == 0 {
+            println!("No words found.");
         }
     }
 
 
-    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
-    pub struct JPWord {
-        pub word: String,
-        pub word_type: Option<WordType>,
-        pub definition: Option<String>,
-        #[serde(default)]
-        next_review: chrono::DateTime<Utc>,
-        #[serde(default)]
-        review_iter: ReviewInterval,
-        #[serde(default)]
-        to_review: bool,
-        #[serde(default)]
-        reviewed_correct: TimesReviewed,
-    }
+    // Add words to the stop list, so they get skipped when files are
+    // processed. If any of them are already in the word list, the user can
+    // have them removed too.
+    pub fn stop_add() {
+        let mut input = String::new();
+        println!("What words should be skipped? You can enter more than one, with spaces between them.");
+        io::stdin().read_line(&mut input).expect("Could not read input");
 
-    impl Default for JPWord {
-        fn default() -> Self {
-            JPWord {
-                word: String::from("default"),
-                word_type: None,
-                definition: None,
-                next_review: Utc::now(),
-                review_iter: ReviewInterval::ThreeHours,
-                to_review: true,
-                reviewed_correct: TimesReviewed::Zero,
+        let locked = lock::acquire();
+        let mut stop_list = load_stop_list();
+        let mut added: Vec<String> = Vec::new();
+        for w in input.split_whitespace() {
+            let key = kana::identity_key(w);
+            if stop_list.iter().any(|s| kana::identity_key(s) == key) {
+                println!("{} is already on the stop list", w);
+                continue;
             }
+            stop_list.push(w.to_string());
+            added.push(key);
         }
-    }
+        stop_list.sort();
+        save_stop_list(&stop_list);
+        drop(locked);
+        println!("Added {} words to the stop list", added.len());
 
-    impl JPWord {
-        pub fn simple_new (w: String) -> JPWord {
-            let _word = JPWord {
-                word: w,
-                word_type: None,
-                definition: None,
-                next_review: Utc::now(),
-                review_iter: ReviewInterval::ThreeHours,
-                to_review: true,
-                reviewed_correct: TimesReviewed::Zero,
-            };
-            _word
+        if added.is_empty() {
+            return
         }
 
-        pub fn get_next_review(&self) -> chrono::DateTime<Utc> {
-            self.next_review
+        let mut store = store::open(true);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+
+        let in_list: Vec<&JPWord> = d_f_words.iter().filter(|w| added.contains(&w.key())).collect();
+        if in_list.is_empty() {
+            return
         }
 
-        pub fn correct(&mut self) -> () {
-            if self.reviewed_correct != TimesReviewed::ThreePlus{
-                self.reviewed_correct = match &self.reviewed_correct {
-                    TimesReviewed::Zero => TimesReviewed::One,
-                    TimesReviewed::One=> TimesReviewed::Two,
-                    TimesReviewed::Two=> {
-                        self.next_review = Utc::now() + self.review_iter.duration();
-                        self.review_iter = self.review_iter.next_dur();
-                        TimesReviewed::ThreePlus
-                    },
-                    TimesReviewed::ThreePlus=> TimesReviewed::ThreePlus,
-                }
-            }
+        let names: Vec<&str> = in_list.iter().map(|w| w.word.as_str()).collect();
+        println!("{} of these are in the word list ({}).", in_list.len(), names.join(", "));
+        println!("Remove them from the word list too? (y/n)");
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer).expect("Could not read input");
+        if !matches!(buffer.trim(), "y" | "Y") {
+            return
+        }
 
-            if self.reviewed_correct == TimesReviewed::ThreePlus {
-                self.to_review = false;
-            }
+        for key in &added {
+            store.remove(key);
         }
+        println!("Succesfully removed the words!");
+        store.save();
+    }
 
-        pub fn set_review(&mut self, b: bool) {
-            if b {
-                self.to_review = b;
-                self.reviewed_correct = TimesReviewed::Zero;
+
+    // Take words off of the stop list.
+    pub fn stop_remove() {
+        if load_stop_list().is_empty() {
+            eprintln!("The stop list is empty!");
+            process::exit(0);
+        }
+
+        let mut input = String::new();
+        println!("What words should be taken off the stop list? You can enter more than one, with spaces between them.");
+        io::stdin().read_line(&mut input).expect("Could not read input");
+
+        // Read it again, in case it changed while the user was typing.
+        let _lock = lock::acquire();
+        let mut stop_list = load_stop_list();
+        let keys: Vec<String> = input.split_whitespace().map(kana::identity_key).collect();
+        let before = stop_list.len();
+        stop_list.retain(|s| !keys.contains(&kana::identity_key(s)));
+        println!("Removed {} words from the stop list", before - stop_list.len());
+
+        save_stop_list(&stop_list);
+    }
+
+
+    // Print everything on the stop list.
+    pub fn print_stop_list() {
+        let stop_list = load_stop_list();
+        if stop_list.is_empty() {
+            println!("The stop list is empty.");
+        }
+        for w in stop_list {
+            println!("{}", w);
+        }
+    }
+
+
+    // Add a tag to a bunch of words at once, or take it off of them. The
+    // user can list the words, or hit enter to use every word that matches
+    // --lesson and --tag.
+    pub fn tag_words(data: &SSData, add: bool) {
+        let mut store = store::open(true);
+        let mut d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+        if d_f_words.is_empty() {
+            eprintln!("File is empty! Can't tag any words...");
+            process::exit(1);
+        }
+
+        let mut tag = String::new();
+        println!("What tag? (like colors or lesson-004)");
+        io::stdin().read_line(&mut tag).expect("Could not read input");
+        let tag = tag.trim();
+        if tag.is_empty() {
+            eprintln!("Nothing entered! Exiting program now");
+            process::exit(1);
+        }
+
+        println!("Which words? You can enter more than one, with spaces between them.");
+        println!("Hit enter to use every word that matches --lesson and --tag.");
+        let input = read_japanese(data.ime);
+
+        let mut indexes: Vec<usize> = Vec::new();
+        if input.trim().is_empty() {
+            indexes = (0..d_f_words.len()).filter(|i| data.filter.matches(&d_f_words[*i])).collect();
+        } else {
+            for w in input.split_whitespace() {
+                match find_word(&d_f_words, w) {
+                    Some(i) => indexes.push(i),
+                    None => println!("{} isn't in the list", w),
+                }
+            }
+        }
+
+        let mut changed = 0;
+        for i in indexes {
+            let w = &mut d_f_words[i];
+            if (add && w.add_tag(tag)) || (!add && w.remove_tag(tag)) {
+                changed += 1;
+                store.upsert(w.clone());
+            }
+        }
+        if add {
+            println!("Tagged {} words with {}", changed, tag);
+        } else {
+            println!("Took {} off of {} words", tag, changed);
+        }
+
+        store.save();
+    }
+
+
+    // Print every tag, and how many words have it.
+    pub fn print_tags() {
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+
+        let mut tags: collections::BTreeMap<&str, usize> = collections::BTreeMap::new();
+        for w in &d_f_words {
+            for t in &w.tags {
+                *tags.entry(t.as_str()).or_default() += 1;
+            }
+        }
+
+        if tags.is_empty() {
+            println!("No words have been tagged yet.");
+            return
+        }
+        for (t, count) in tags {
+            println!("{}  ({} words)", t, count);
+        }
+    }
+
+
+    // Upgrade the word list to the newest version, and say what changed.
+    pub fn migrate_file() {
+        let (mut store, report) = store::open_with_report(true);
+
+        if report.changes.is_empty() {
+            println!("The word list is already up to date (version {}).", migrate::VERSION);
+            return
+        }
+
+        println!("Upgraded the word list from version {} to {}:", report.from, migrate::VERSION);
+        for c in &report.changes {
+            println!("  {}", c);
+        }
+        store.save();
+    }
+
+
+    // Print the backups of the word list, newest first.
+    pub fn list_backups() {
+        let backups = backup::list();
+        if backups.is_empty() {
+            println!("There aren't any backups yet. One is made every time the list is saved.");
+            return
+        }
+
+        for (i, b) in backups.iter().enumerate() {
+            let when = match backup::made_at(b) {
+                Some(t) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
+                None => b.display().to_string(),
+            };
+            // Only JSON backups can be looked inside without opening them.
+            if b.extension().is_some_and(|e| e == "json") {
+                let words = fs::read_to_string(b).ok()
+                    .and_then(|f| migrate::load(&f).ok())
+                    .map_or(String::from("can't be read"), |(c, _)| format!("{} words", c.words.len()));
+                println!("{:<2} -> {}  ({})", i + 1, when, words);
             } else {
-                self.to_review = b;
+                println!("{:<2} -> {}", i + 1, when);
+            }
+        }
+        println!("\nThey're kept in {}", backup::dir().display());
+    }
+
+
+    // Put a backup from list_backups back in place of the word list.
+    pub fn restore_backup(n: usize) {
+        let backups = backup::list();
+        let chosen = match n.checked_sub(1).and_then(|i| backups.get(i)) {
+            Some(b) => b,
+            None => {
+                eprintln!("There's no backup {}. Try backup list to see them.", n);
+                process::exit(1);
+            }
+        };
+
+        // Don't put back something that can't be loaded.
+        if chosen.extension().is_some_and(|e| e == "json") {
+            let f_words = fs::read_to_string(chosen).expect("Could not read file");
+            if let Err(e) = migrate::load(&f_words) {
+                eprintln!("That backup can't be read: {}", e);
+                process::exit(1);
+            }
+        }
+
+        if let Err(e) = backup::restore(chosen) {
+            eprintln!("Could not restore the backup: {}", e);
+            process::exit(1);
+        }
+        println!("Restored the word list from {}.", chosen.display());
+        println!("The list that was there before is now backup 1.");
+    }
+
+
+    // Print every profile.
+    pub fn list_profiles() {
+        let profiles = profile::list();
+        if profiles.is_empty() {
+            println!("There aren't any profiles yet. Make one with profile create <name>.");
+            return
+        }
+
+        for (name, p) in profiles {
+            if p.shared {
+                println!("{}  (shares the main word list)", name);
+            } else {
+                println!("{}", name);
+            }
+        }
+        println!("\nThey're kept in {}", profile::dir().display());
+    }
+
+
+    // Make a new profile. It starts with an empty word list, unless it
+    // shares the main one.
+    pub fn create_profile(name: &str, shared: bool) {
+        if !profile::valid_name(name) {
+            eprintln!("Profile names can only have letters, numbers, - and _.");
+            process::exit(1);
+        }
+        if profile::read(name).is_some() {
+            eprintln!("There's already a profile called {}!", name);
+            process::exit(1);
+        }
+
+        profile::create(name, &profile::Profile { shared })
+            .expect("Could not make the profile");
+        println!("Made the profile {}! Add --profile {} to use it.", name, name);
+        if shared {
+            println!("It uses the main word list, but keeps its own review progress.");
+        }
+    }
+
+
+    // Make a new profile that starts out the same as another one.
+    pub fn copy_profile(from: &str, to: &str) {
+        if profile::read(from).is_none() {
+            eprintln!("There's no profile called {}. Try profile list to see them.", from);
+            process::exit(1);
+        }
+        if !profile::valid_name(to) {
+            eprintln!("Profile names can only have letters, numbers, - and _.");
+            process::exit(1);
+        }
+        if profile::path(to).exists() {
+            eprintln!("There's already a profile called {}!", to);
+            process::exit(1);
+        }
+
+        profile::copy(from, to).expect("Could not copy the profile");
+        println!("Copied {} to {}.", from, to);
+    }
+
+
+    // Delete a profile and everything in it, after checking with the user.
+    pub fn delete_profile(name: &str) {
+        if profile::read(name).is_none() {
+            eprintln!("There's no profile called {}. Try profile list to see them.", name);
+            process::exit(1);
+        }
+
+        println!("This deletes {} and everything in it, including its backups. Are you sure? (y/n)", name);
+        let mut buffer = String::new();
+        io::stdin().read_line(&mut buffer).expect("Could not read input");
+        if !matches!(buffer.trim(), "y" | "Y") {
+            println!("Left {} alone.", name);
+            return
+        }
+
+        profile::delete(name).expect("Could not delete the profile");
+        println!("Deleted {}.", name);
+    }
+
+
+    // Quiz the user on conjugating a random verb from the list.
+    pub fn conjugate_quiz(data: &SSData) {
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+        if d_f_words.is_empty() {
+            eprintln!("File is empty! Can't quiz any words...");
+            process::exit(1);
+        }
+
+        // Verbs are words marked as verbs, or words that were seen 
+        // conjugated, as long as they look like a verb. Anything that ends up
+        // with no forms can't be asked about.
+        let verbs: Vec<(&JPWord, VerbClass)> = d_f_words.iter()
+            .filter_map(|w| match w.word_type {
+                Some(t) => t.verb_class(&w.word).map(|c| (w, c)),
+                None if !w.forms.is_empty() => conjugate::guess_class(&w.word).map(|c| (w, c)),
+                None => None,
+            })
+            .filter(|(w, c)| !conjugate::all_forms(&w.word, *c).is_empty())
+            .collect();
+
+        if verbs.is_empty() {
+            eprintln!("There aren't any verbs in the list yet!");
+            process::exit(1);
+        }
+
+        let mut rng = rand::thread_rng();
+        let (verb, class) = verbs[rng.gen_range(0..verbs.len())];
+        let forms = conjugate::all_forms(&verb.word, class);
+        let (form, answer) = &forms[rng.gen_range(0..forms.len())];
+
+        println!("Here is a test!");
+        println!("What is the {} of {} ({})?", form, verb.word, class);
+
+        let buffer = read_japanese(data.ime);
+
+        if kana::identity_key(buffer.trim()) == kana::identity_key(answer) {
+            println!("\nCorrect!!!");
+            return
+        }
+
+        println!("\nKeep trying!!! The answer is:\n{}", answer);
+        println!("\nHere are all of the forms of {}:", verb.word);
+        for (f, a) in &forms {
+            println!("{}  {}", a, f);
+        }
+    }
+
+
+    // Print every lesson, and how many words are in it.
+    pub fn print_lessons() {
+        let store = store::open(false);
+        let d_f_words: Vec<JPWord> = store.query(&WordFilter::default());
+
+        let mut lessons: Vec<(LessonDate, usize)> = Vec::new();
+        for w in &d_f_words {
+            for d in &w.lessons {
+                match lessons.iter_mut().find(|(l, _)| l.same_day(d)) {
+                    Some((_, count)) => *count += 1,
+                    None => lessons.push((d.clone(), 1)),
+                }
+            }
+        }
+        lessons.sort_by_key(|(l, _)| (l.month, l.day));
+
+        if lessons.is_empty() {
+            println!("No lessons found yet.");
+        }
+        for (l, count) in lessons {
+            println!("{}  {} words", l, count);
+        }
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // The line, what's left of it, and the (word, reading) pairs.
+        type Case<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+        #[test]
+        fn readings() {
+            let cases: [Case; 8] = [
+                // Full width brackets.
+                ("コーヒー\u{FF08}こーひー\u{FF09}", "コーヒー ", &[("コーヒー", "こーひー")]),
+                // Normal ones.
+                ("テレビ(てれび)", "テレビ ", &[("テレビ", "てれび")]),
+                // Or one of each.
+                ("テレビ\u{FF08}てれび)", "テレビ ", &[("テレビ", "てれび")]),
+                ("ちゃ(おちゃ\u{FF09}をのむ", "ちゃ をのむ", &[("ちゃ", "おちゃ")]),
+                // Katakana readings, and more than one on a line.
+                ("お茶（おちゃ）\u{3000}水（ミズ）", "お茶 \u{3000}水 ", &[("お茶", "おちゃ"), ("水", "ミズ")]),
+                // Brackets without kana in them stay.
+                ("は（wa)", "は（wa)", &[]),
+                ("Tea (おちゃ)", "Tea (おちゃ)", &[]),
+                // A bracket that's never closed.
+                ("テレビ（てれび", "テレビ（てれび", &[]),
+            ];
+            for (line, rest, pairs) in cases {
+                let pairs: Vec<(String, String)> = pairs.iter()
+                    .map(|(w, r)| (w.to_string(), r.to_string()))
+                    .collect();
+                assert_eq!(split_readings(line), (rest.to_string(), pairs), "{}", line);
+            }
+        }
+    }
+}
+
+
+// Everything needed to process everything.
+mod structures {
+    use serde::{Serialize, Deserialize};
+    use core::fmt;
+    use std::cmp::Ordering;
+    use chrono::{Duration, Utc, Weekday};
+    use crate::conjugate;
+    use crate::kana;
+    use crate::romaji;
+
+
+    // Parts of speech, the japanese way. Verbs are split up by how they
+    // conjugate, and adjectives by whether they take い or な.
+    //
+    // The first versions of sear_stone used english parts of speech. Those
+    // are turned into these when the word file is read, see from_legacy.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone, Copy)]
+    pub enum WordType {
+        Noun,
+        Pronoun,
+        Particle,
+        IAdjective,
+        NaAdjective,
+        IchidanVerb,
+        GodanVerb,
+        IrregularVerb,
+        Adverb,
+        Counter,
+        Prefix,
+        Suffix,
+        Conjunction,
+        Interjection,
+        Adnominal,
+        Auxiliary,
+        Expression,
+    }
+
+    // Na adjectives that end in い anyways.
+    const NA_ENDING_IN_I: [&str; 8] = [
+        "きれい", "ゆうめい", "きらい", "とくい", "しつれい", "ていねい",
+        "あいまい", "さいわい",
+    ];
+
+    impl WordType {
+        // Every type, in the order they're shown in the menu.
+        pub const ALL: [WordType; 17] = [
+            WordType::Noun, WordType::Pronoun, WordType::Particle,
+            WordType::IAdjective, WordType::NaAdjective, WordType::IchidanVerb,
+            WordType::GodanVerb, WordType::IrregularVerb, WordType::Adverb,
+            WordType::Counter, WordType::Prefix, WordType::Suffix,
+            WordType::Conjunction, WordType::Interjection, WordType::Adnominal,
+            WordType::Auxiliary, WordType::Expression,
+        ];
+
+        // Turns one of the old english types into a japanese one. Verb and
+        // Adjective need the word itself to figure out which kind they are.
+        // Returns None if old isn't one of the old types that changed.
+        pub fn from_legacy(old: &str, word: &str) -> Option<WordType> {
+            match old {
+                // Words like 勉強 were marked as verbs because they go with
+                // する, but on their own they're nouns.
+                "Verb" => Some(match conjugate::guess_class(word) {
+                    Some(class) => WordType::from_verb_class(class),
+                    None => WordType::Noun,
+                }),
+                "Adjective" => Some(WordType::adjective(word)),
+                // Japanese has particles where english has prepositions.
+                "Preposition" => Some(WordType::Particle),
+                // この and その are the closest thing to articles.
+                "Article" => Some(WordType::Adnominal),
+                "Quantifier" => Some(WordType::Counter),
+                "Phrase" => Some(WordType::Expression),
+                _ => None,
+            }
+        }
+
+        // Which kind of adjective a word is, if it's an adjective at all.
+        pub fn adjective(word: &str) -> WordType {
+            if word.ends_with('い') && !NA_ENDING_IN_I.contains(&word) {
+                WordType::IAdjective
+            } else {
+                WordType::NaAdjective
+            }
+        }
+
+        // The type for a verb that conjugates like class.
+        pub fn from_verb_class(class: VerbClass) -> WordType {
+            match class {
+                VerbClass::Ichidan => WordType::IchidanVerb,
+                VerbClass::Godan => WordType::GodanVerb,
+                VerbClass::Suru | VerbClass::Kuru => WordType::IrregularVerb,
+            }
+        }
+
+        // How the word conjugates, if it's a verb.
+        pub fn verb_class(&self, word: &str) -> Option<VerbClass> {
+            let class = match self {
+                WordType::IchidanVerb => VerbClass::Ichidan,
+                WordType::GodanVerb => VerbClass::Godan,
+                WordType::IrregularVerb if word.ends_with("する") => VerbClass::Suru,
+                WordType::IrregularVerb => VerbClass::Kuru,
+                _ => return None,
+            };
+            // Someone could've marked 勉強 as a verb, but it can't be conjugated
+            // without the する.
+            if conjugate::fits(word, class) {
+                Some(class)
+            } else {
+                None
+            }
+        }
+    }
+
+    impl fmt::Display for WordType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                WordType::Noun => "Noun (名詞)",
+                WordType::Pronoun => "Pronoun (代名詞)",
+                WordType::Particle => "Particle (助詞, は, を, に)",
+                WordType::IAdjective => "い-adjective (たかい)",
+                WordType::NaAdjective => "な-adjective (しずか)",
+                WordType::IchidanVerb => "Ichidan verb (たべる)",
+                WordType::GodanVerb => "Godan verb (のむ)",
+                WordType::IrregularVerb => "Irregular verb (する, くる)",
+                WordType::Adverb => "Adverb (副詞)",
+                WordType::Counter => "Counter (～じ, ～がつ)",
+                WordType::Prefix => "Prefix (お～, ご～)",
+                WordType::Suffix => "Suffix (～さん, ～いろ)",
+                WordType::Conjunction => "Conjunction (でも, そして)",
+                WordType::Interjection => "Interjection (はい, ええ)",
+                WordType::Adnominal => "Adnominal (この, その)",
+                WordType::Auxiliary => "Auxiliary (です, ます)",
+                WordType::Expression => "Expression (おはよう)",
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+    // How a verb conjugates.
+    //  - Ichidan verbs drop the る (たべる -> たべます).
+    //  - Godan verbs change the last kana (のむ -> のみます).
+    //  - する and くる are irregular.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone, Copy)]
+    pub enum VerbClass {
+        Ichidan,
+        Godan,
+        Suru,
+        Kuru,
+    }
+
+    impl fmt::Display for VerbClass {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                VerbClass::Ichidan => write!(f, "ichidan"),
+                VerbClass::Godan => write!(f, "godan"),
+                VerbClass::Suru => write!(f, "する verb"),
+                VerbClass::Kuru => write!(f, "くる verb"),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+    enum TimesReviewed {
+        Zero,
+        One,
+        Two,
+        ThreePlus,
+    }
+
+    #[allow(clippy::derivable_impls)]
+    impl Default for TimesReviewed {
+        fn default() -> Self {
+            TimesReviewed::Zero
+        }
+    }
+
+
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+    enum ReviewInterval {
+        ThreeHours,
+        OneDay,
+        ThreeDays,
+        OneWeek,
+        TwoWeeks,
+        OneMonth,
+        ThreeMonths,
+        SixMonths,
+        OneYear,
+    }
+
+    #[allow(clippy::derivable_impls)]
+    impl Default for ReviewInterval {
+        fn default() -> Self {
+            ReviewInterval::ThreeHours
+        }
+    }
+
+    impl ReviewInterval {
+        fn duration(&self) -> chrono::Duration {
+            match self {
+                ReviewInterval::ThreeHours => Duration::hours(3),
+                ReviewInterval::OneDay => Duration::days(1),
+                ReviewInterval::ThreeDays=> Duration::days(3),
+                ReviewInterval::OneWeek=> Duration::weeks(1),
+                ReviewInterval::TwoWeeks=> Duration::weeks(2),
+                ReviewInterval::OneMonth=> Duration::weeks(4),
+                ReviewInterval::ThreeMonths=> Duration::weeks(12),
+                ReviewInterval::SixMonths=> Duration::weeks(24),
+                ReviewInterval::OneYear=> Duration::weeks(52),
+            }
+        }
+
+        fn next_dur(&mut self) -> ReviewInterval {
+            match self {
+                ReviewInterval::ThreeHours => ReviewInterval::OneDay,
+                ReviewInterval::OneDay=> ReviewInterval::ThreeDays,
+                ReviewInterval::ThreeDays=> ReviewInterval::OneWeek,
+                ReviewInterval::OneWeek=> ReviewInterval::TwoWeeks,
+                ReviewInterval::TwoWeeks=> ReviewInterval::OneMonth,
+                ReviewInterval::OneMonth=> ReviewInterval::ThreeMonths,
+                ReviewInterval::ThreeMonths=> ReviewInterval::SixMonths,
+                ReviewInterval::SixMonths=> ReviewInterval::OneYear,
+                ReviewInterval::OneYear=> ReviewInterval::OneYear,
+            }
+        }
+    }
+
+
+    // Where a word is at in reviews. Profiles that share a word list keep
+    // their own copy of this for every word.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+    pub struct ReviewState {
+        next_review: chrono::DateTime<Utc>,
+        review_iter: ReviewInterval,
+        to_review: bool,
+        reviewed_correct: TimesReviewed,
+    }
+
+    impl Default for ReviewState {
+        // Same as a word that was just added.
+        fn default() -> Self {
+            ReviewState {
+                next_review: Utc::now(),
+                review_iter: ReviewInterval::ThreeHours,
+                to_review: true,
+                reviewed_correct: TimesReviewed::Zero,
+            }
+        }
+    }
+
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
+    pub struct JPWord {
+        pub word: String,
+        #[serde(default)]
+        pub reading: Option<String>,
+        #[serde(default)]
+        pub romaji: Option<String>,
+        pub word_type: Option<WordType>,
+        #[serde(default)]
+        pub senses: Vec<Sense>,
+        #[serde(default)]
+        next_review: chrono::DateTime<Utc>,
+        #[serde(default)]
+        review_iter: ReviewInterval,
+        #[serde(default)]
+        to_review: bool,
+        #[serde(default)]
+        reviewed_correct: TimesReviewed,
+        #[serde(default)]
+        pub sentences: Vec<u32>,
+        #[serde(default)]
+        pub occurrences: Vec<Occurrence>,
+        #[serde(default)]
+        pub lessons: Vec<LessonDate>,
+        #[serde(default)]
+        pub alternates: Vec<String>,
+        #[serde(default)]
+        pub forms: Vec<String>,
+        #[serde(default)]
+        pub suggested_type: Option<WordType>,
+        #[serde(default)]
+        pub tags: Vec<String>,
+    }
+
+    impl Default for JPWord {
+        fn default() -> Self {
+            JPWord {
+                word: String::from("default"),
+                reading: None,
+                romaji: None,
+                word_type: None,
+                senses: Vec::new(),
+                next_review: Utc::now(),
+                review_iter: ReviewInterval::ThreeHours,
+                to_review: true,
+                reviewed_correct: TimesReviewed::Zero,
+                sentences: Vec::new(),
+                occurrences: Vec::new(),
+                lessons: Vec::new(),
+                alternates: Vec::new(),
+                forms: Vec::new(),
+                suggested_type: None,
+                tags: Vec::new(),
+            }
+        }
+    }
+
+    impl JPWord {
+        #[allow(clippy::let_and_return)]
+        pub fn simple_new (w: String) -> JPWord {
+            let _word = JPWord {
+                word: w,
+                reading: None,
+                romaji: None,
+                word_type: None,
+                senses: Vec::new(),
+                next_review: Utc::now(),
+                review_iter: ReviewInterval::ThreeHours,
+                to_review: true,
+                reviewed_correct: TimesReviewed::Zero,
+                sentences: Vec::new(),
+                occurrences: Vec::new(),
+                lessons: Vec::new(),
+                alternates: Vec::new(),
+                forms: Vec::new(),
+                suggested_type: None,
+                tags: Vec::new(),
+            };
+            _word
+        }
+
+        // What identifies the word. Spellings with the same key are the same
+        // word.
+        pub fn key(&self) -> String {
+            kana::identity_key(&self.word)
+        }
+
+        // Merges another copy of the same word into this one. Whatever this
+        // word already has wins, the other word only fills in the blanks.
+        pub fn absorb(&mut self, other: JPWord) {
+            if other.word != self.word && !self.alternates.contains(&other.word) {
+                self.alternates.push(other.word);
+            }
+            for a in other.alternates {
+                if a != self.word && !self.alternates.contains(&a) {
+                    self.alternates.push(a);
+                }
+            }
+            if self.word_type.is_none() {
+                self.word_type = other.word_type;
+            }
+            if self.reading.is_none() {
+                self.reading = other.reading;
+                self.romaji = other.romaji;
+            }
+            for sense in other.senses {
+                self.add_sense(sense);
+            }
+            for id in other.sentences {
+                if !self.sentences.contains(&id) {
+                    self.sentences.push(id);
+                }
+            }
+            for o in other.occurrences {
+                self.add_occurrence(o);
+            }
+            for l in other.lessons {
+                if !self.lessons.contains(&l) {
+                    self.lessons.push(l);
+                }
+            }
+            for f in other.forms {
+                if f != self.word && !self.forms.contains(&f) {
+                    self.forms.push(f);
+                }
+            }
+            for t in other.tags {
+                self.add_tag(&t);
+            }
+        }
+
+        // Tags the word, unless it already has that tag. Returns whether it
+        // was added.
+        pub fn add_tag(&mut self, tag: &str) -> bool {
+            if self.tags.iter().any(|t| t == tag) {
+                return false
+            }
+            self.tags.push(tag.to_string());
+            self.tags.sort();
+            true
+        }
+
+        // Returns whether the word had the tag.
+        pub fn remove_tag(&mut self, tag: &str) -> bool {
+            let before = self.tags.len();
+            self.tags.retain(|t| t != tag);
+            self.tags.len() != before
+        }
+
+        // Sets how the word is read, and the romaji that goes with it.
+        pub fn set_reading(&mut self, reading: &str) {
+            self.reading = Some(reading.trim().to_string());
+            self.romaji = Some(romaji::to_romaji(reading.trim(), romaji::System::Hepburn));
+        }
+
+        // Words written only in kana are read the way they're written, so
+        // they don't need anyone to fill the reading in.
+        pub fn fill_reading(&mut self) {
+            match self.reading.clone() {
+                Some(r) if self.romaji.is_none() => self.set_reading(&r),
+                Some(_) => (),
+                None if !self.has_kanji() => {
+                    let word = self.word.clone();
+                    self.set_reading(&word);
+                }
+                None => (),
+            }
+        }
+
+        pub fn has_kanji(&self) -> bool {
+            self.word.chars().any(|c| JapaneseWordType::of(c) == JapaneseWordType::Kanji)
+        }
+
+        // Adds a meaning to the word, unless it already has one with the
+        // same gloss. Returns whether it was added.
+        pub fn add_sense(&mut self, sense: Sense) -> bool {
+            if self.senses.iter().any(|s| s.gloss == sense.gloss) {
+                return false
+            }
+            self.senses.push(sense);
+            true
+        }
+
+        // All the meanings on one line, for lists.
+        pub fn glosses(&self) -> String {
+            let glosses: Vec<&str> = self.senses.iter().map(|s| s.gloss.as_str()).collect();
+            glosses.join("; ")
+        }
+
+        // Remember that the word was seen somewhere. Seeing the same line of
+        // the same file again (like when a lesson is ingested twice) doesn't
+        // add it twice.
+        pub fn add_occurrence(&mut self, o: Occurrence) {
+            if !self.occurrences.contains(&o) {
+                self.occurrences.push(o);
+            }
+        }
+
+        pub fn get_next_review(&self) -> chrono::DateTime<Utc> {
+            self.next_review
+        }
+
+        #[allow(clippy::unused_unit)]
+        pub fn correct(&mut self) -> () {
+            if self.reviewed_correct != TimesReviewed::ThreePlus{
+                self.reviewed_correct = match &self.reviewed_correct {
+                    TimesReviewed::Zero => TimesReviewed::One,
+                    TimesReviewed::One=> TimesReviewed::Two,
+                    TimesReviewed::Two=> {
+                        self.next_review = Utc::now() + self.review_iter.duration();
+                        self.review_iter = self.review_iter.next_dur();
+                        TimesReviewed::ThreePlus
+                    },
+                    TimesReviewed::ThreePlus=> TimesReviewed::ThreePlus,
+                }
+            }
+
+            if self.reviewed_correct == TimesReviewed::ThreePlus {
+                self.to_review = false;
+            }
+        }
+
+        pub fn set_review(&mut self, b: bool) {
+            if b {
+                self.to_review = b;
+                self.reviewed_correct = TimesReviewed::Zero;
+            } else {
+                self.to_review = b;
+            }
+        }
+
+        #[allow(clippy::let_and_return, clippy::needless_match)]
+        pub fn get_review(&self) -> bool {
+            let a = match self.to_review {
+                true => true,
+                false => false,
+            };
+            a
+        }
+
+        pub fn review_state(&self) -> ReviewState {
+            ReviewState {
+                next_review: self.next_review,
+                review_iter: self.review_iter.clone(),
+                to_review: self.to_review,
+                reviewed_correct: self.reviewed_correct.clone(),
+            }
+        }
+
+        pub fn set_review_state(&mut self, r: ReviewState) {
+            self.next_review = r.next_review;
+            self.review_iter = r.review_iter;
+            self.to_review = r.to_review;
+            self.reviewed_correct = r.reviewed_correct;
+        }
+    }
+
+    impl PartialOrd for JPWord {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+        
+    impl Ord for JPWord {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.word.cmp(&other.word)
+        }
+    }
+
+    impl fmt::Display for JPWord {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.word)
+        }
+    }
+
+
+    // The date of a lesson. The headers don't have a year, so neither does
+    // this.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
+    pub struct LessonDate {
+        pub month: u32,
+        pub day: u32,
+        pub weekday: Option<Weekday>,
+    }
+
+    impl LessonDate {
+        // Weekdays get left off of some headers, so they aren't compared.
+        pub fn same_day(&self, other: &LessonDate) -> bool {
+            self.month == other.month && self.day == other.day
+        }
+    }
+
+    impl fmt::Display for LessonDate {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}/{}", self.month, self.day)?;
+            if let Some(w) = &self.weekday {
+                write!(f, " ({})", w)?;
+            }
+            Ok(())
+        }
+    }
+
+
+    // Where a word was seen. line starts at 1, and context is the whole line
+    // the word was on.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
+    pub struct Occurrence {
+        pub file: String,
+        pub line: usize,
+        pub context: String,
+    }
+
+    impl fmt::Display for Occurrence {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}:{}  {}", self.file, self.line, self.context)
+        }
+    }
+
+
+    // One meaning of a word. A lot of words have more than one (きん is
+    // gold, and also the color gold), and they aren't always the same type
+    // of word. word_type is None when it's the same as the word's.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
+    pub struct Sense {
+        pub gloss: String,
+        #[serde(default)]
+        pub word_type: Option<WordType>,
+        #[serde(default)]
+        pub note: Option<String>,
+    }
+
+    impl Sense {
+        pub fn new(gloss: &str) -> Sense {
+            Sense {
+                gloss: gloss.trim().to_string(),
+                word_type: None,
+                note: None,
+            }
+        }
+    }
+
+    // Shows like "gold [Noun (名詞)] - the metal".
+    impl fmt::Display for Sense {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.gloss)?;
+            if let Some(t) = self.word_type {
+                write!(f, " [{}]", t)?;
+            }
+            if let Some(n) = &self.note {
+                write!(f, " - {}", n)?;
+            }
+            Ok(())
+        }
+    }
+
+
+    // An example sentence from a lesson. In the lessons, these are the lines
+    // that start with ●, and the english is on the line after.
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
+    pub struct Sentence {
+        pub id: u32,
+        pub text: String,
+        pub translation: Option<String>,
+        pub source: String,
+    }
+
+    impl Sentence {
+        // If the first line is an example sentence, returns the sentence and
+        // its translation. The translation is every line after it, up until
+        // an empty line, another sentence, or a line with japanese in it.
+        pub fn from_lines(lines: &[&str]) -> Option<(String, Option<String>)> {
+            let text = lines.first()?.trim().strip_prefix('\u{25CF}')?.trim();
+            if text.is_empty() {
+                return None
+            }
+
+            let translation: Vec<&str> = lines[1..].iter()
+                .map(|l| l.trim())
+                .take_while(|l| !l.is_empty() && !l.starts_with('\u{25CF}'))
+                .take_while(|l| l.chars().all(|c| JapaneseWordType::of(c) == JapaneseWordType::Neither))
+                .collect();
+
+            let translation = if translation.is_empty() {
+                None
+            } else {
+                Some(translation.join("\n"))
+            };
+            Some((text.to_string(), translation))
+        }
+    }
+
+    impl fmt::Display for Sentence {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "\u{25CF}{}", self.text)?;
+            if let Some(t) = &self.translation {
+                write!(f, "\n{}", t)?;
+            }
+            Ok(())
+        }
+    }
+
+
+    // JapaneseWordType is used to help the program know when what it's 
+    // reading is Hiragana, Katakana, Kanji, or neither. It's important, 
+    // because words must be of the same type. Once the type changes, it must
+    // be a new word, or not a word at all. The one exception is kanji 
+    // followed by hiragana, which is how okurigana (食べ, 高い) is written.
+    #[derive(PartialEq, Clone, Copy, Debug)]
+    pub enum JapaneseWordType {
+        Hiragana,
+        Katakana,
+        Kanji,
+        Neither,
+    }
+
+    impl JapaneseWordType {
+        // Figure out which type a single char is.
+        pub fn of(c: char) -> JapaneseWordType {
+            match c {
+                // 々 repeats the previous kanji and ヶ is used as a counter,
+                // so both behave like kanji. ヶ has to be checked before the
+                // katakana block, since that's where it lives.
+                '\u{3005}' | '\u{30F6}' => JapaneseWordType::Kanji,
+                '\u{3040}'..='\u{309F}' => JapaneseWordType::Hiragana,
+                '\u{30A0}'..='\u{30FF}' => JapaneseWordType::Katakana,
+                // CJK Unified Ideographs, Extension A, the compatibility
+                // block, and Extensions B through F.
+                '\u{4E00}'..='\u{9FFF}'
+                | '\u{3400}'..='\u{4DBF}'
+                | '\u{F900}'..='\u{FAFF}'
+                | '\u{20000}'..='\u{2EBEF}' => JapaneseWordType::Kanji,
+                _ => JapaneseWordType::Neither,
+            }
+        }
+    }
+
+
+    // IngestMode decides what counts as the end of a word.
+    //
+    // Delimited is for documents like the lessons, where words are already
+    // split up by the teacher with spaces (usually the full width U+3000 
+    // one). Anything that isn't japanese, so spaces, punctuation, english,
+    // and numbers, ends the word. Changing between hiragana, katakana and
+    // kanji does not.
+    //
+    // Script is the old way, for text that isn't spaced. A word ends when
+    // the type of char changes.
+    //
+    // Segmented is also for text that isn't spaced, like news articles. It
+    // splits on spaces like Delimited, and then looks up known words to
+    // split whatever is left. See segmenter::Segmenter.
+    #[derive(PartialEq, Clone, Copy, Debug)]
+    pub enum IngestMode {
+        Delimited,
+        Script,
+        Segmented,
+    }
+
+    impl IngestMode {
+        // Turns what the user typed after --mode into a mode.
+        pub fn from_arg(s: &str) -> Option<IngestMode> {
+            match s {
+                "spaced" | "delimited" => Some(IngestMode::Delimited),
+                "script" => Some(IngestMode::Script),
+                "dictionary" | "segmented" => Some(IngestMode::Segmented),
+                _ => None,
+            }
+        }
+    }
+
+
+    // JapaneseWordParser is used to parser together japanese words. It's 
+    // meant to be used by feeding it a stream of chars. Based on the input
+    // of chars, it will either 1) add the char to the word, 2) do nothing
+    // if it is not a word, or 3) return a word if the word has ended. When a
+    // word ends depends on the mode, see IngestMode.
+    //
+    // Once the stream is done, call finish to get whatever word is left.
+    pub struct JapaneseWordParser {
+        pub mode: IngestMode,
+        pub word_type: JapaneseWordType,
+        pub word: String,
+        pub changed: bool,
+    }
+
+    impl JapaneseWordParser {
+        pub fn new(mode: IngestMode) -> Self {
+            JapaneseWordParser {
+                mode,
+                word_type: JapaneseWordType::Neither,
+                word: String::new(),
+                changed: false,
+            }
+        }
+
+        // main parser. Takes in a char, returns nothing if it's the same type,
+        // adds a char to the current word being processes 
+        // (JapaneseWordParser.word) or it returns a new word if the type has
+        // changed.
+        pub fn add_to_word(&mut self, s: char) -> Option<String> {
+            if self.mode == IngestMode::Delimited {
+                return self.add_delimited(s)
+            }
+
+            let t = JapaneseWordType::of(s);
+
+            // The long vowel mark (ー) lives in the katakana block, but 
+            // こーひー uses it too, so it just stretches whatever word it's in.
+            if s == '\u{30FC}' && self.word_type != JapaneseWordType::Neither {
+                self.changed = false;
+                self.word.push(s);
+                return None
+            }
+
+            // Okurigana stays with the kanji in front of it. After that the
+            // word is hiragana, so a kanji after it starts a new word.
+            if self.word_type == JapaneseWordType::Kanji && t == JapaneseWordType::Hiragana {
+                self.word_type = JapaneseWordType::Hiragana;
+                self.changed = false;
+                self.word.push(s);
+                return None
+            }
+
+            self.switch_word_type(t, s)
+        }
+
+        // add_to_word for IngestMode::Delimited. Every japanese char is
+        // added to the word, no matter the type. The katakana middle dot (・)
+        // is punctuation, even though it lives in the katakana block.
+        fn add_delimited(&mut self, s: char) -> Option<String> {
+            let t = JapaneseWordType::of(s);
+            if t == JapaneseWordType::Neither || s == '\u{30FB}' || s == '\u{30A0}' {
+                return self.switch_word_type(JapaneseWordType::Neither, ' ')
+            }
+
+            // Which japanese type doesn't matter here, so one is as good as
+            // any other.
+            self.switch_word_type(JapaneseWordType::Hiragana, s)
+        }
+
+        // Returns the last word, if there is one, and resets the parser.
+        pub fn finish(&mut self) -> Option<String> {
+            self.switch_word_type(JapaneseWordType::Neither, ' ')
+        }
+
+
+        // used by JapaneseWordParser.add_to_word  If the type changes, the
+        // word that was being built is done. It gets returned and a new word
+        // is started with the char that caused the change. Non japanese chars
+        // never start a word.
+        //
+        // If nothing has changed, just add the char to the word.
+        fn switch_word_type(&mut self, t: JapaneseWordType, c: char) -> Option<String> {
+            if self.word_type == t {
+                self.changed = false;
+                if t != JapaneseWordType::Neither {
+                    self.word.push(c);
+                }
+                return None
+            }
+
+            self.changed = true;
+            self.word_type = t;
+            let final_word = std::mem::take(&mut self.word);
+            if t != JapaneseWordType::Neither {
+                self.word.push(c);
+            }
+
+            if final_word.is_empty() {
+                None
+            } else {
+                Some(final_word)
+            }
+        }
+    }
+}
+
+
+// Splits up japanese text that doesn't have spaces between the words, using a
+// list of words that are already known (the lexicon).
+mod segmenter {
+    use std::collections::HashSet;
+    use std::{fs, io};
+
+
+    // What each piece of a split costs. The cheapest way to split the text
+    // wins, so known words are cheap and unknown chars are expensive. An
+    // unknown char right after another one costs less than starting a new
+    // unknown piece, so unknown stuff stays together as one word instead of
+    // being chopped into single chars.
+    //
+    // A known word is still cheaper than one more unknown char, so particles
+    // like は get split off the front of words we don't know yet. Splitting
+    // a known word out of the middle of an unknown one means starting a new
+    // unknown piece after it though, which costs more than keeping it 
+    // together. So まいにち doesn't get chopped up just because に is known.
+    const KNOWN_COST: u32 = 10;
+    const UNKNOWN_START_COST: u32 = 15;
+    const UNKNOWN_CONTINUE_COST: u32 = 12;
+
+
+    pub struct Segmenter {
+        words: HashSet<String>,
+        max_len: usize,
+    }
+
+    impl Segmenter {
+        pub fn new() -> Self {
+            Segmenter {
+                words: HashSet::new(),
+                max_len: 0,
+            }
+        }
+
+        pub fn add_word(&mut self, w: &str) {
+            let w = w.trim();
+            if w.is_empty() {
+                return
+            }
+            self.max_len = self.max_len.max(w.chars().count());
+            self.words.insert(w.to_string());
+        }
+
+        // Adds every word in a dictionary file to the lexicon, and returns
+        // how many there were. The file has one word per line. Anything 
+        // after the first space or tab (like a definition) is ignored, as are
+        // empty lines and lines starting with #.
+        pub fn load_dictionary(&mut self, path: &str) -> io::Result<usize> {
+            let contents = fs::read_to_string(path)?;
+            let mut count = 0;
+            for line in contents.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if let Some(w) = line.split(['\t', ' ', '\u{3000}']).next() {
+                    self.add_word(w);
+                    count += 1;
+                }
+            }
+            Ok(count)
+        }
+
+        // Splits text into words. This finds the cheapest path through the
+        // text (Viterbi), where every step is either a known word or a single
+        // unknown char. Unknown chars that end up next to each other are
+        // joined back into one word at the end.
+        pub fn segment(&self, text: &str) -> Vec<String> {
+            let chars: Vec<char> = text.chars().collect();
+            let n = chars.len();
+            if n == 0 {
+                return Vec::new()
+            }
+
+            // best[i][u] is the cheapest way to split the first i chars, 
+            // where u says if the last piece was an unknown char. Each entry
+            // holds the cost and where the last piece started.
+            let mut best: Vec<[Option<(u32, usize, usize)>; 2]> = vec![[None, None]; n + 1];
+            best[0][0] = Some((0, 0, 0));
+
+            for i in 0..n {
+                for u in 0..2 {
+                    let cost = match best[i][u] {
+                        Some((c, _, _)) => c,
+                        None => continue,
+                    };
+
+                    // Every known word starting here.
+                    for len in 1..=self.max_len.min(n - i) {
+                        let piece: String = chars[i..i + len].iter().collect();
+                        if self.words.contains(&piece) {
+                            relax(&mut best[i + len][0], cost + KNOWN_COST, i, u);
+                        }
+                    }
+
+                    // Or just one char we don't know.
+                    let step = if u == 1 { UNKNOWN_CONTINUE_COST } else { UNKNOWN_START_COST };
+                    relax(&mut best[i + 1][1], cost + step, i, u);
+                }
+            }
+
+            // Walk back from the end to collect the pieces.
+            let mut pieces: Vec<(String, bool)> = Vec::new();
+            let mut i = n;
+            let mut u = match (best[n][0], best[n][1]) {
+                (Some((a, _, _)), Some((b, _, _))) if b < a => 1,
+                (None, _) => 1,
+                _ => 0,
+            };
+            while i > 0 {
+                let (_, start, prev_u) = best[i][u].expect("Every position is reachable");
+                pieces.push((chars[start..i].iter().collect(), u == 1));
+                i = start;
+                u = prev_u;
+            }
+            pieces.reverse();
+
+            // Put unknown chars that are next to each other back together.
+            let mut words: Vec<String> = Vec::new();
+            let mut last_unknown = false;
+            for (piece, unknown) in pieces {
+                if unknown && last_unknown {
+                    if let Some(w) = words.last_mut() {
+                        w.push_str(&piece);
+                    }
+                } else {
+                    words.push(piece);
+                }
+                last_unknown = unknown;
+            }
+            words
+        }
+    }
+
+
+    // Keeps whichever way to get somewhere is cheaper.
+    fn relax(slot: &mut Option<(u32, usize, usize)>, cost: u32, start: usize, prev_u: usize) {
+        match slot {
+            Some((c, _, _)) if *c <= cost => (),
+            _ => *slot = Some((cost, start, prev_u)),
+        }
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn lexicon() -> Segmenter {
+            let mut s = Segmenter::new();
+            for w in ["わたし", "は", "に", "を", "が", "です", "がくせい", "パン", "食べる", "食べます", "日本", "日本語", "いきます"] {
+                s.add_word(w);
+            }
+            s
+        }
+
+        #[test]
+        fn known_sentences() {
+            // The text, and how it should be split.
+            let cases: [(&str, &[&str]); 6] = [
+                ("わたしはがくせいです", &["わたし", "は", "がくせい", "です"]),
+                ("パンを食べる", &["パン", "を", "食べる"]),
+                // The okurigana stays on the verb.
+                ("パンを食べます", &["パン", "を", "食べます"]),
+                // The longest known word wins.
+                ("日本語です", &["日本語", "です"]),
+                ("", &[]),
+                ("わたし", &["わたし"]),
+            ];
+            for (text, expected) in cases {
+                assert_eq!(lexicon().segment(text), expected, "{}", text);
+            }
+        }
+
+        #[test]
+        fn unknown_words() {
+            let cases: [(&str, &[&str]); 3] = [
+                // Words that aren't in the lexicon stay in one piece, even
+                // with a known に in the middle.
+                ("まいにちいきます", &["まいにち", "いきます"]),
+                ("わたしはせんせいです", &["わたし", "は", "せんせい", "です"]),
+                ("きのうパンを食べました", &["きのう", "パン", "を", "食べました"]),
+            ];
+            for (text, expected) in cases {
+                assert_eq!(lexicon().segment(text), expected, "{}", text);
+            }
+        }
+    }
+}
+
+
+// Pulls definitions out of lesson lines that put a japanese word right next to
+// its english meaning, like あかいろ　Red or Monday   げつようび.
+mod glosses {
+    use crate::structures::JapaneseWordType;
+
+
+    // One chunk of a line.
+    enum Piece {
+        Japanese(String),
+        English(String),
+        Other,
+    }
+
+
+    // Returns every (japanese, english) pair on a line. Lines can be in
+    // either order, but the whole line has to use the same order. So if the
+    // line starts with japanese, each japanese word gets the english after 
+    // it, and if it starts with english, each japanese word gets the english
+    // in front of it.
+    //
+    // Example sentences (lines starting with ●) are skipped, their english is
+    // on the next line and is a whole sentence anyways.
+    pub fn extract(line: &str) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        let line = line.trim();
+        if line.starts_with('\u{25CF}') {
+            return pairs
+        }
+
+        let items = group(split(line));
+        let jp_first = match items.iter().find(|i| !matches!(i, Piece::Other)) {
+            Some(Piece::Japanese(_)) => true,
+            Some(Piece::English(_)) => false,
+            _ => return pairs,
+        };
+
+        for (i, item) in items.iter().enumerate() {
+            let jp = match item {
+                Piece::Japanese(j) => j,
+                _ => continue,
+            };
+
+            // When there is more than one english phrase next to the word,
+            // use the closest one if it's before the word, and the last one
+            // if it's after. That skips romaji like "Murasaki iro" in
+            // むらさきいろ　Murasaki iro    Purple.
+            let en = if jp_first {
+                items[i + 1..].iter()
+                    .take_while(|p| matches!(p, Piece::English(_)))
+                    .last()
+            } else {
+                items[..i].iter().rev()
+                    .take_while(|p| matches!(p, Piece::English(_)))
+                    .next()
+            };
+
+            if let Some(Piece::English(e)) = en {
+                pairs.push((jp.clone(), e.clone()));
+            }
+        }
+        pairs
+    }
+
+
+    // Splits the line up on spaces, and wherever japanese runs into english.
+    // Each chunk comes with whether the gap in front of it was wide (a full
+    // width space, more than one space, or a change of language), which is
+    // what separates one english phrase from another.
+    fn split(line: &str) -> Vec<(String, bool)> {
+        let mut chunks: Vec<(String, bool)> = Vec::new();
+        let mut current = String::new();
+        let mut gap = String::new();
+
+        for c in line.chars() {
+            if c.is_whitespace() {
+                if !current.is_empty() {
+                    let wide = is_wide(&gap);
+                    chunks.push((std::mem::take(&mut current), wide));
+                    gap.clear();
+                }
+                gap.push(c);
+                continue;
+            }
+
+            // Only japanese counts as a change of language. Punctuation
+            // like … belongs to the english it's stuck to.
+            if let Some(last) = current.chars().last() {
+                if (c.is_ascii_alphabetic() && is_japanese(last)) || (last.is_ascii_alphabetic() && is_japanese(c)) {
+                    let wide = is_wide(&gap);
+                    chunks.push((std::mem::take(&mut current), wide));
+                    gap = String::from("\u{3000}");
+                }
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            let wide = is_wide(&gap);
+            chunks.push((current, wide));
+        }
+        chunks
+    }
+
+
+    // Turns the chunks into pieces, joining english words that are only one
+    // space apart into a single phrase.
+    fn group(chunks: Vec<(String, bool)>) -> Vec<Piece> {
+        let mut items: Vec<Piece> = Vec::new();
+        for (chunk, wide) in chunks {
+            if chunk.chars().any(|c| c.is_ascii_alphabetic()) && !chunk.chars().any(is_japanese) {
+                if let Some(Piece::English(e)) = items.last_mut() {
+                    if !wide {
+                        e.push(' ');
+                        e.push_str(&chunk);
+                        continue;
+                    }
+                }
+                items.push(Piece::English(chunk));
+            } else {
+                items.push(match japanese_word(&chunk) {
+                    Some(j) => Piece::Japanese(j),
+                    None => Piece::Other,
+                });
+            }
+        }
+        items
+    }
+
+
+    // A chunk is a japanese word if it's only japanese chars, leaving out a
+    // reading in brackets, like the （おちゃ） in ちゃ（おちゃ）.
+    fn japanese_word(chunk: &str) -> Option<String> {
+        let word: String = match chunk.find(['\u{FF08}', '(']) {
+            Some(i) => chunk[..i].to_string(),
+            None => chunk.to_string(),
+        };
+        if !word.is_empty() && word.chars().all(is_japanese) {
+            Some(word)
+        } else {
+            None
+        }
+    }
+
+
+    fn is_japanese(c: char) -> bool {
+        JapaneseWordType::of(c) != JapaneseWordType::Neither
+    }
+
+
+    fn is_wide(gap: &str) -> bool {
+        gap.chars().count() > 1 || gap.chars().any(|c| c != ' ')
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn pairs(list: &[(&str, &str)]) -> Vec<(String, String)> {
+            list.iter().map(|(j, e)| (j.to_string(), e.to_string())).collect()
+        }
+
+        #[test]
+        fn lesson_lines() {
+            let cases: [(&str, &[(&str, &str)]); 9] = [
+                // Japanese first, with a full width space.
+                ("あかいろ\u{3000}Red", &[("あかいろ", "Red")]),
+                // English first, with a few spaces.
+                ("Monday   げつようび", &[("げつようび", "Monday")]),
+                // Romaji in between is skipped.
+                ("むらさきいろ\u{3000}Murasaki iro    Purple", &[("むらさきいろ", "Purple")]),
+                // More than one pair on a line.
+                ("あか\u{3000}Red\u{3000}あお\u{3000}Blue", &[("あか", "Red"), ("あお", "Blue")]),
+                // A reading in brackets is left off the word.
+                ("ちゃ（おちゃ）\u{3000}Tea", &[("ちゃ", "Tea")]),
+                // No space between the japanese and the english.
+                ("みずWater", &[("みず", "Water")]),
+                // Parentheses in the english stay closed.
+                ("    なん\u{3000}Number answer (What time, What page, What number\u{2026})",
+                    &[("なん", "Number answer (What time, What page, What number\u{2026})")]),
+                ("なに\u{3000}Word answer (What do you do, What did you do)",
+                    &[("なに", "Word answer (What do you do, What did you do)")]),
+                // Example sentences are skipped.
+                ("\u{25CF}なんじ\u{3000}ですか？", &[]),
+            ];
+            for (line, expected) in cases {
+                assert_eq!(extract(line), pairs(expected), "{}", line);
+            }
+        }
+
+        #[test]
+        fn no_pairs() {
+            assert!(extract("").is_empty());
+            assert!(extract("What time is it?").is_empty());
+            assert!(extract("わたし\u{3000}は\u{3000}がくせい").is_empty());
+        }
+    }
+}
+
+
+// Reads the dates at the top of lessons, like 6がつ　２１にち　すいようび or
+// ７がつ　１２にち　すいようび. Numbers can be full width or normal, and the
+// kanji versions (6月21日(水)) work too.
+mod dates {
+    use chrono::Weekday;
+    use crate::structures::LessonDate;
+
+
+    // Days of the month that are read without a number.
+    const DAY_NAMES: [(&str, u32); 11] = [
+        ("ついたち", 1), ("ふつか", 2), ("みっか", 3), ("よっか", 4),
+        ("いつか", 5), ("むいか", 6), ("なのか", 7), ("ようか", 8),
+        ("ここのか", 9), ("とおか", 10), ("はつか", 20),
+    ];
+
+    // How each weekday starts, in kana and kanji. The ようび/曜日 after it is
+    // optional.
+    const WEEKDAYS: [(&str, Weekday); 14] = [
+        ("げつ", Weekday::Mon), ("か", Weekday::Tue), ("すい", Weekday::Wed),
+        ("もく", Weekday::Thu), ("きん", Weekday::Fri), ("ど", Weekday::Sat),
+        ("にち", Weekday::Sun), ("月", Weekday::Mon), ("火", Weekday::Tue),
+        ("水", Weekday::Wed), ("木", Weekday::Thu), ("金", Weekday::Fri),
+        ("土", Weekday::Sat), ("日", Weekday::Sun),
+    ];
+
+
+    // Returns the date if the whole line is a date header. It needs at least
+    // a month and a day, and a weekday can come after them. Anything else on
+    // the line (like です, or a ● in front) means it's not a header.
+    pub fn parse_header(line: &str) -> Option<LessonDate> {
+        // Get rid of the spaces and make the numbers normal.
+        let s: String = line.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                '\u{FF10}'..='\u{FF19}' => char::from_u32(c as u32 - 0xFF10 + '0' as u32).unwrap_or(c),
+                _ => c,
+            })
+            .collect();
+
+        let (month, rest) = number(&s)?;
+        let rest = rest.strip_prefix("がつ").or_else(|| rest.strip_prefix('月'))?;
+
+        let (day, rest) = match number(rest) {
+            Some((d, r)) => (d, r.strip_prefix("にち").or_else(|| r.strip_prefix('日'))?),
+            None => DAY_NAMES.iter()
+                .find_map(|(name, d)| rest.strip_prefix(name).map(|r| (*d, r)))?,
+        };
+        let rest = rest.strip_prefix('日').unwrap_or(rest);
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None
+        }
+
+        let rest = rest.trim_matches(|c| matches!(c, '(' | ')' | '\u{FF08}' | '\u{FF09}'));
+        let weekday = if rest.is_empty() {
+            None
+        } else {
+            Some(weekday(rest)?)
+        };
+
+        Some(LessonDate { month, day, weekday })
+    }
+
+
+    // Reads a date typed on the command line. Either month/day (6/21) or a
+    // header (6がつ21にち).
+    pub fn parse_arg(s: &str) -> Option<LessonDate> {
+        if let Some((m, d)) = s.split_once('/') {
+            let month: u32 = m.trim().parse().ok()?;
+            let day: u32 = d.trim().parse().ok()?;
+            if (1..=12).contains(&month) && (1..=31).contains(&day) {
+                return Some(LessonDate { month, day, weekday: None })
+            }
+            return None
+        }
+        parse_header(s)
+    }
+
+
+    // Reads the number at the start of s, and returns it with the rest.
+    fn number(s: &str) -> Option<(u32, &str)> {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let n = s[..end].parse().ok()?;
+        Some((n, &s[end..]))
+    }
+
+
+    fn weekday(s: &str) -> Option<Weekday> {
+        WEEKDAYS.iter().find_map(|(name, w)| {
+            let rest = s.strip_prefix(name)?;
+            match rest {
+                "" | "ようび" | "曜日" | "曜" => Some(*w),
+                _ => None,
+            }
+        })
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn date(month: u32, day: u32, weekday: Option<Weekday>) -> Option<LessonDate> {
+            Some(LessonDate { month, day, weekday })
+        }
+
+        #[test]
+        fn headers() {
+            let cases = [
+                // The ones from the lessons, with full width and ascii numbers.
+                ("6がつ　２１にち　すいようび", date(6, 21, Some(Weekday::Wed))),
+                ("７がつ　１２にち　すいようび", date(7, 12, Some(Weekday::Wed))),
+                ("6月21日(水)", date(6, 21, Some(Weekday::Wed))),
+                ("１２がつ　ついたち", date(12, 1, None)),
+                ("4がつ はつか げつ", date(4, 20, Some(Weekday::Mon))),
+                ("10月3日", date(10, 3, None)),
+                // Not headers.
+                ("6がつ　２１にち　です", None),
+                ("●6がつ　２１にち", None),
+                ("13がつ　1にち", None),
+                ("6がつ　32にち", None),
+                ("6がつ", None),
+                ("あかいろ　Red", None),
+            ];
+            for (line, expected) in cases {
+                assert_eq!(parse_header(line), expected, "{}", line);
+            }
+        }
+
+        #[test]
+        fn args() {
+            assert_eq!(parse_arg("6/21"), date(6, 21, None));
+            assert_eq!(parse_arg("7がつ12にち"), date(7, 12, None));
+            assert_eq!(parse_arg("13/1"), None);
+            assert_eq!(parse_arg("june"), None);
+        }
+    }
+}
+
+
+// Helpers for working with kana.
+mod kana {
+    use unicode_normalization::UnicodeNormalization;
+
+
+    // Turns a word into the key that's used to tell if two words are the
+    // same. Two spellings that only differ by
+    //  - full vs half width (ｺｰﾋｰ and コーヒー),
+    //  - katakana vs hiragana (コーヒー and こーひー),
+    //  - or how a long vowel is written (こーひー and こおひい)
+    // get the same key.
+    pub fn identity_key(s: &str) -> String {
+        let mut key = String::new();
+        for c in s.trim().nfkc() {
+            let c = to_hiragana(c);
+            if c == '\u{30FC}' {
+                // The long vowel mark repeats the vowel before it.
+                if let Some(v) = key.chars().last().and_then(vowel) {
+                    key.push(v);
+                    continue;
+                }
+            }
+            key.push(c);
+        }
+        key
+    }
+
+
+    // Turns a katakana char into its hiragana version. Anything else comes
+    // back the same.
+    pub fn to_hiragana(c: char) -> char {
+        match c {
+            '\u{30A1}'..='\u{30F6}' | '\u{30FD}' | '\u{30FE}' => {
+                char::from_u32(c as u32 - 0x60).unwrap_or(c)
+            }
+            _ => c,
+        }
+    }
+
+
+    // Turns a hiragana char into its katakana version. Anything else comes
+    // back the same.
+    pub fn to_katakana(c: char) -> char {
+        match c {
+            '\u{3041}'..='\u{3096}' | '\u{309D}' | '\u{309E}' => {
+                char::from_u32(c as u32 + 0x60).unwrap_or(c)
+            }
+            _ => c,
+        }
+    }
+
+
+    // The rows of the kana table, in あいうえお order. The わ row is the one
+    // godan verbs ending in う use (かわない, かいます, かう, かえる, かおう), so
+    // it has い, う, え and お instead of the old ゐ, ゑ and を.
+    const ROWS: [[char; 5]; 15] = [
+        ['あ', 'い', 'う', 'え', 'お'],
+        ['か', 'き', 'く', 'け', 'こ'],
+        ['が', 'ぎ', 'ぐ', 'げ', 'ご'],
+        ['さ', 'し', 'す', 'せ', 'そ'],
+        ['ざ', 'じ', 'ず', 'ぜ', 'ぞ'],
+        ['た', 'ち', 'つ', 'て', 'と'],
+        ['だ', 'ぢ', 'づ', 'で', 'ど'],
+        ['な', 'に', 'ぬ', 'ね', 'の'],
+        ['は', 'ひ', 'ふ', 'へ', 'ほ'],
+        ['ば', 'び', 'ぶ', 'べ', 'ぼ'],
+        ['ぱ', 'ぴ', 'ぷ', 'ぺ', 'ぽ'],
+        ['ま', 'み', 'む', 'め', 'も'],
+        ['ら', 'り', 'る', 'れ', 'ろ'],
+        ['や', 'い', 'ゆ', 'え', 'よ'],
+        ['わ', 'い', 'う', 'え', 'お'],
+    ];
+
+    // Which column (0 = あ, 1 = い, ...) a hiragana char is in.
+    pub fn column(c: char) -> Option<usize> {
+        ROWS.iter().find_map(|row| row.iter().position(|k| *k == c))
+    }
+
+    // Moves a hiragana char to another column of the same row, like み -> む.
+    // Since あ, い, う, え and お are in more than one row, those always go to
+    // the わ row (う -> わ), which is what verbs need.
+    pub fn shift(c: char, column: usize) -> Option<char> {
+        let row = match c {
+            'い' | 'う' | 'え' | 'お' => &ROWS[14],
+            _ => ROWS.iter().find(|row| row.contains(&c))?,
+        };
+        row.get(column).copied()
+    }
+
+
+    // The vowel a hiragana char ends with, if it has one.
+    pub fn vowel(c: char) -> Option<char> {
+        const ROWS: [(&str, char); 5] = [
+            ("あかさたなはまやらわがざだばぱぁゃゎ", 'あ'),
+            ("いきしちにひみりぎじぢびぴぃ", 'い'),
+            ("うくすつぬふむゆるぐずづぶぷぅゅゔ", 'う'),
+            ("えけせてねへめれげぜでべぺぇ", 'え'),
+            ("おこそとのほもよろをごぞどぼぽぉょ", 'お'),
+        ];
+        ROWS.iter().find(|(row, _)| row.contains(c)).map(|(_, v)| *v)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn keys() {
+            // The word, then the key it should get.
+            let cases = [
+                // Every way of writing coffee is the same word.
+                ("コーヒー", "こおひい"),
+                ("ｺｰﾋｰ", "こおひい"),
+                ("こーひー", "こおひい"),
+                ("こおひい", "こおひい"),
+                // Half width kana with a separate ﾞ or ﾟ get joined up.
+                ("ｶﾞｯｺｳ", "がっこう"),
+                ("ﾊﾟﾝ", "ぱん"),
+                // Other things NFKC folds.
+                ("ＴＶ", "TV"),
+                ("㌔", "きろ"),
+                (" たべる ", "たべる"),
+                // Kanji stay as they are, only the kana around them change.
+                ("日本語", "日本語"),
+                ("食ベル", "食べる"),
+                // A long vowel mark with nothing before it is kept.
+                ("ー", "ー"),
+            ];
+            for (word, key) in cases {
+                assert_eq!(identity_key(word), key, "{}", word);
+            }
+            assert_ne!(identity_key("日本"), identity_key("にほん"));
+        }
+    }
+}
+
+
+// Figures out the dictionary form of inflected verbs and adjectives, like
+// たべませんでした -> たべる or たかくない -> たかい.
+//
+// Without a real dictionary it's easy to get this wrong (is きた "came" or
+// "north"?), so the only endings that are always trusted are the polite ます
+// ones. Everything else only counts if the dictionary form it comes up with
+// is a word we already know.
+mod deinflect {
+    use std::collections::HashSet;
+    use crate::kana;
+
+
+    // Endings of the polite forms, longest first. Each one is cut off to get
+    // the stem (たべ).
+    const MASU_ENDINGS: [&str; 11] = [
+        "ませんでしたか", "ませんでした", "ましょうか", "ましたか", "ませんか",
+        "ましょう", "ました", "ません", "まして", "ますか", "ます",
+    ];
+
+    // Endings of i-adjectives, with whether they're safe to always trust.
+    const ADJECTIVE_ENDINGS: [(&str, bool); 8] = [
+        ("くありませんでした", true), ("くありません", true),
+        ("くなかった", false), ("くないです", true), ("くない", false),
+        ("かったです", true), ("かった", false), ("くて", false),
+    ];
+
+    // Common ichidan verbs that end in いる. In the ます form these look just
+    // like godan verbs (みます could be みる or む), so these win over godan
+    // when there's nothing else to go on.
+    const ICHIDAN_I: [&str; 18] = [
+        "いる", "みる", "きる", "にる", "おきる", "できる", "かりる", "おりる",
+        "あびる", "いきる", "おちる", "すぎる", "たりる", "しんじる", "とじる",
+        "のびる", "もちいる", "かんじる",
+    ];
+
+
+    // Set phrases that look inflected, but should be learned as they are.
+    // Anything with one of these in it is a set phrase too, so ございます
+    // covers おめでとうございます and どうもありがとうございます.
+    const EXPRESSIONS: [&str; 24] = [
+        "すみません", "ありがとうございます", "ありがとうございました",
+        "おはようございます", "いただきます", "ごちそうさまでした",
+        "はじめまして", "おねがいします", "しつれいします", "しつれいしました",
+        "いってきます", "おじゃまします", "よろしくおねがいします",
+        "おつかれさまでした", "ございます", "ございました", "ごめんなさい",
+        "ください", "おやすみなさい", "いってらっしゃい", "いらっしゃいませ",
+        "どういたしまして", "こんにちは", "こんばんは",
+    ];
+
+
+    // One guess at a dictionary form. Certain guesses are used even when the
+    // word isn't known yet.
+    struct Candidate {
+        lemma: String,
+        certain: bool,
+    }
+
+
+    // Whether word is a set phrase (see EXPRESSIONS).
+    pub fn is_expression(word: &str) -> bool {
+        let key = kana::identity_key(word);
+        EXPRESSIONS.iter().any(|e| key.contains(e))
+    }
+
+
+    // Whether word ends in one of the polite ます endings (see MASU_ENDINGS).
+    pub fn is_polite(word: &str) -> bool {
+        MASU_ENDINGS.iter().any(|e| word.ends_with(e))
+    }
+
+
+    // Returns the dictionary form of word, if it looks inflected. known has
+    // the keys (see kana::identity_key) of every word we know about. A word
+    // that's known itself is left alone, so かえる doesn't turn into かう.
+    pub fn deinflect(word: &str, known: &HashSet<String>) -> Option<String> {
+        if is_expression(word) || known.contains(&kana::identity_key(word)) {
+            return None
+        }
+
+        let candidates = candidates(word);
+        let is_known = |c: &&Candidate| known.contains(&kana::identity_key(&c.lemma));
+
+        candidates.iter().find(is_known)
+            .or_else(|| candidates.iter().find(|c| c.certain))
+            .map(|c| c.lemma.clone())
+            .filter(|l| l != word)
+    }
+
+
+    // Every dictionary form word could come from, best guess first.
+    fn candidates(word: &str) -> Vec<Candidate> {
+        let mut out: Vec<Candidate> = Vec::new();
+
+        // Polite forms: たべます, のみませんでした, しましたか
+        if let Some(stem) = MASU_ENDINGS.iter().find_map(|e| word.strip_suffix(e)) {
+            from_masu_stem(stem, &mut out);
+            return out
+        }
+
+        // Adjectives: たかくない, たかかった
+        for (ending, certain) in ADJECTIVE_ENDINGS {
+            if let Some(stem) = word.strip_suffix(ending) {
+                if !stem.is_empty() {
+                    out.push(Candidate { lemma: format!("{}い", stem), certain });
+                }
+            }
+        }
+
+        // Plain questions: たべるか
+        if let Some(stem) = word.strip_suffix('か') {
+            if !stem.is_empty() {
+                out.push(Candidate { lemma: stem.to_string(), certain: false });
+                for c in candidates(stem) {
+                    out.push(Candidate { lemma: c.lemma, certain: false });
+                }
+            }
+        }
+
+        // Plain negative: たべない, のまない, たべなかった
+        for ending in ["なかった", "ない"] {
+            if let Some(stem) = word.strip_suffix(ending) {
+                match stem {
+                    "し" => guess(&mut out, "する"),
+                    "こ" => guess(&mut out, "くる"),
+                    _ => {
+                        guess(&mut out, &format!("{}る", stem));
+                        // Godan verbs use the あ column (かかない -> かく).
+                        if stem.chars().last().and_then(kana::column) == Some(0) {
+                            if let Some(l) = change_last(stem, 2) {
+                                guess(&mut out, &l);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Plain past and te form: たべた, のんだ, かって
+        for (endings, dakuten) in [(["た", "て"], false), (["だ", "で"], true)] {
+            for ending in endings {
+                if let Some(stem) = word.strip_suffix(ending) {
+                    past_stem(stem, dakuten, &mut out);
+                }
+            }
+        }
+
+        // Potential: たべられる. Godan potentials (のめる, はいれる) look just
+        // like other verbs (かける, いれる), so they're left alone.
+        if let Some(stem) = word.strip_suffix("られる") {
+            guess(&mut out, &format!("{}る", stem));
+        }
+
+        out
+    }
+
+
+    // Guesses the dictionary form from the ます stem (たべ, のみ, し).
+    fn from_masu_stem(stem: &str, out: &mut Vec<Candidate>) {
+        let last = match stem.chars().last() {
+            Some(c) => c,
+            None => return,
+        };
+
+        match stem {
+            "し" => return certain(out, "する"),
+            "き" | "来" => return certain(out, "くる"),
+            _ => (),
+        }
+
+        // Potential forms (たべられます) only count if the plain verb is
+        // known. Like above, のめます is left alone.
+        if let Some(s) = stem.strip_suffix("られ") {
+            guess(out, &format!("{}る", s));
+        }
+
+        let ichidan = format!("{}る", stem);
+        match kana::column(last) {
+            // べんきょうします -> べんきょうする, but はなします -> はなす.
+            Some(1) if last == 'し' => {
+                let noun = &stem[..stem.len() - 'し'.len_utf8()];
+                let suru = format!("{}する", noun);
+                let godan = change_last(stem, 2).unwrap_or_default();
+                if looks_like_suru_noun(noun) {
+                    certain(out, &suru);
+                    guess(out, &godan);
+                } else {
+                    certain(out, &godan);
+                    guess(out, &suru);
+                }
+            }
+            // のみ -> のむ, unless it's one of the ichidan verbs like みる.
+            Some(1) => {
+                if ICHIDAN_I.contains(&ichidan.as_str()) {
+                    certain(out, &ichidan);
+                }
+                certain(out, &change_last(stem, 2).unwrap_or_default());
+                guess(out, &ichidan);
+            }
+            // たべ -> たべる
+            Some(3) => certain(out, &ichidan),
+            // Kanji stems, like 見 in 見ます, are ichidan.
+            None => certain(out, &ichidan),
+            _ => (),
+        }
+    }
+
+
+    // Nouns that go with する (べんきょう, りょこう, 勉強) are usually made of
+    // chinese readings, which are longer and full of ん and small kana. Stems
+    // of godan verbs ending in す (はな, かえ, 話) are usually short.
+    fn looks_like_suru_noun(noun: &str) -> bool {
+        let kanji = noun.chars().all(|c| kana::column(c).is_none() && !c.is_ascii());
+        noun.chars().count() >= 3
+            || noun.contains(['ん', 'ゃ', 'ゅ', 'ょ'])
+            || (kanji && noun.chars().count() >= 2)
+    }
+
+
+    // Guesses the dictionary form from what's left after cutting off た/て
+    // (or だ/で, when dakuten is true).
+    fn past_stem(stem: &str, dakuten: bool, out: &mut Vec<Candidate>) {
+        let mut chars = stem.chars();
+        let last = match chars.next_back() {
+            Some(c) => c,
+            None => return,
+        };
+        let rest = chars.as_str();
+
+        if !dakuten {
+            match stem {
+                "し" => guess(out, "する"),
+                "き" => guess(out, "くる"),
+                "いっ" => guess(out, "いく"),
+                _ => (),
+            }
+        }
+
+        let endings: &[char] = match (last, dakuten) {
+            ('っ', false) => &['う', 'つ', 'る'],
+            ('ん', true) => &['む', 'ぶ', 'ぬ'],
+            ('い', false) => &['く'],
+            ('い', true) => &['ぐ'],
+            ('し', false) => &['す'],
+            _ => &[],
+        };
+        for e in endings {
+            guess(out, &format!("{}{}", rest, e));
+        }
+
+        // Ichidan verbs just add た (たべた).
+        if !dakuten && !rest.is_empty() {
+            guess(out, &format!("{}る", stem));
+        }
+    }
+
+
+    // Changes the last kana of s to another column, like のみ -> のむ.
+    fn change_last(s: &str, column: usize) -> Option<String> {
+        let mut chars = s.chars();
+        let last = chars.next_back()?;
+        let rest = chars.as_str();
+        if rest.is_empty() {
+            return None
+        }
+        Some(format!("{}{}", rest, kana::shift(last, column)?))
+    }
+
+
+    // Adds a guess that only counts if the word is known.
+    fn guess(out: &mut Vec<Candidate>, lemma: &str) {
+        if !lemma.is_empty() {
+            out.push(Candidate { lemma: lemma.to_string(), certain: false });
+        }
+    }
+
+
+    // Adds a guess that's good enough to use on its own.
+    fn certain(out: &mut Vec<Candidate>, lemma: &str) {
+        if !lemma.is_empty() {
+            out.push(Candidate { lemma: lemma.to_string(), certain: true });
+        }
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn dictionary_forms() {
+            // The word, the words that are already known, and what it should
+            // be saved under.
+            let cases: [(&str, &[&str], Option<&str>); 27] = [
+                ("たべます", &[], Some("たべる")),
+                ("たべませんでした", &[], Some("たべる")),
+                ("たべましたか", &[], Some("たべる")),
+                ("のみました", &[], Some("のむ")),
+                ("みます", &[], Some("みる")),
+                ("します", &[], Some("する")),
+                ("きます", &[], Some("くる")),
+                ("べんきょうします", &[], Some("べんきょうする")),
+                ("はなします", &[], Some("はなす")),
+                ("かえります", &["かえる"], Some("かえる")),
+                ("たかくないです", &[], Some("たかい")),
+                ("たかくない", &["たかい"], Some("たかい")),
+                ("のまない", &["のむ"], Some("のむ")),
+                ("たべた", &["たべる"], Some("たべる")),
+                ("のんだ", &["のむ"], Some("のむ")),
+                ("たべられる", &["たべる"], Some("たべる")),
+                // Set phrases stay the way they are.
+                ("すみません", &[], None),
+                ("おめでとうございます", &[], None),
+                ("どうもありがとうございます", &[], None),
+                // Different verbs that only look like each other.
+                ("かえる", &["かう"], None),
+                ("かける", &["かく"], None),
+                ("あける", &["あく"], None),
+                ("つける", &["つく"], None),
+                ("かけない", &["かく"], None),
+                ("かえます", &["かう"], Some("かえる")),
+                // Words that are already known are left alone.
+                ("かえる", &["かう", "かえる"], None),
+                ("たべます", &["たべます"], None),
+            ];
+            for (word, known, expected) in cases {
+                let known: HashSet<String> = known.iter().map(|k| kana::identity_key(k)).collect();
+                assert_eq!(deinflect(word, &known).as_deref(), expected, "{}", word);
+            }
+        }
+    }
+}
+
+
+// Conjugates verbs from their dictionary form, like たべる -> たべませんでした.
+mod conjugate {
+    use core::fmt;
+    use crate::kana;
+    use crate::structures::VerbClass;
+
+
+    // The forms that get practiced.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Form {
+        Polite,
+        PoliteNegative,
+        PolitePast,
+        PolitePastNegative,
+        Negative,
+        Past,
+        PastNegative,
+        Te,
+        Potential,
+        Volitional,
+    }
+
+    const FORMS: [Form; 10] = [
+        Form::Polite, Form::PoliteNegative, Form::PolitePast,
+        Form::PolitePastNegative, Form::Negative, Form::Past,
+        Form::PastNegative, Form::Te, Form::Potential, Form::Volitional,
+    ];
+
+    impl fmt::Display for Form {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                Form::Polite => "polite form (ます)",
+                Form::PoliteNegative => "polite negative (ません)",
+                Form::PolitePast => "polite past (ました)",
+                Form::PolitePastNegative => "polite past negative (ませんでした)",
+                Form::Negative => "plain negative (ない)",
+                Form::Past => "plain past (た)",
+                Form::PastNegative => "plain past negative (なかった)",
+                Form::Te => "te form (て)",
+                Form::Potential => "potential form (can do)",
+                Form::Volitional => "volitional form (let's)",
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+
+    // Godan verbs ending in る that look like ichidan verbs.
+    const GODAN_RU: [&str; 10] = [
+        "かえる", "はいる", "しる", "はしる", "へる", "しゃべる", "すべる",
+        "まいる", "にぎる", "いじる",
+    ];
+
+
+    // Guesses how a verb conjugates from its dictionary form. Returns None
+    // if it doesn't look like a verb at all.
+    pub fn guess_class(verb: &str) -> Option<VerbClass> {
+        let last = verb.chars().last()?;
+        let before = verb.chars().rev().nth(1);
+
+        if verb.ends_with("する") {
+            return Some(VerbClass::Suru)
+        }
+        if verb == "くる" || verb.ends_with("来る") {
+            return Some(VerbClass::Kuru)
+        }
+        // です is a copula, not a verb, even though it ends in す.
+        if verb == "です" || verb.chars().count() < 2 {
+            return None
+        }
+        if last == 'る' && !GODAN_RU.contains(&verb) {
+            match before.and_then(kana::column) {
+                // い and え before the る (たべる, みる), or kanji (見る).
+                Some(1) | Some(3) | None => return Some(VerbClass::Ichidan),
+                _ => (),
+            }
+        }
+        if kana::column(last) == Some(2) && "うくぐすつぬぶむる".contains(last) {
+            return Some(VerbClass::Godan)
+        }
+        None
+    }
+
+
+    // Whether a verb ends the way its class needs it to. If it doesn't,
+    // none of the forms can be made.
+    pub fn fits(verb: &str, class: VerbClass) -> bool {
+        let last = match verb.chars().last() {
+            Some(c) => c,
+            None => return false,
+        };
+        match class {
+            // い or え before the る, or kanji (見る).
+            VerbClass::Ichidan => match verb.chars().rev().nth(1) {
+                Some(before) if last == 'る' => {
+                    matches!(kana::column(before), Some(1) | Some(3) | None)
+                }
+                _ => false,
+            },
+            VerbClass::Godan => "うくぐすつぬぶむる".contains(last),
+            VerbClass::Suru => verb.ends_with("する"),
+            VerbClass::Kuru => verb == "くる" || verb.ends_with("来る"),
+        }
+    }
+
+
+    // Every form of a verb, in the order of FORMS.
+    pub fn all_forms(verb: &str, class: VerbClass) -> Vec<(Form, String)> {
+        FORMS.iter()
+            .filter_map(|f| conjugate(verb, class, *f).map(|c| (*f, c)))
+            .collect()
+    }
+
+
+    // Conjugates a single form. Returns None if the verb doesn't end the way
+    // its class should.
+    pub fn conjugate(verb: &str, class: VerbClass, form: Form) -> Option<String> {
+        let mut chars = verb.chars();
+        let last = chars.next_back()?;
+        let stem = chars.as_str();
+
+        let masu_stem = match class {
+            VerbClass::Ichidan => stem.to_string(),
+            VerbClass::Godan => format!("{}{}", stem, kana::shift(last, 1)?),
+            VerbClass::Suru => format!("{}し", verb.strip_suffix("する")?),
+            VerbClass::Kuru => kuru(verb, 'き')?,
+        };
+
+        // The stem for ない. Godan verbs use the あ column (のま), and ある
+        // is just ない.
+        let nai_stem = match class {
+            VerbClass::Ichidan => stem.to_string(),
+            VerbClass::Godan if verb == "ある" => String::new(),
+            VerbClass::Godan => format!("{}{}", stem, kana::shift(last, 0)?),
+            VerbClass::Suru => format!("{}し", verb.strip_suffix("する")?),
+            VerbClass::Kuru => kuru(verb, 'こ')?,
+        };
+
+        let conjugated = match form {
+            Form::Polite => format!("{}ます", masu_stem),
+            Form::PoliteNegative => format!("{}ません", masu_stem),
+            Form::PolitePast => format!("{}ました", masu_stem),
+            Form::PolitePastNegative => format!("{}ませんでした", masu_stem),
+            Form::Negative => format!("{}ない", nai_stem),
+            Form::PastNegative => format!("{}なかった", nai_stem),
+            Form::Past => ta_form(verb, class, false)?,
+            Form::Te => ta_form(verb, class, true)?,
+            // ある has no potential form, there's no あれる.
+            Form::Potential if verb == "ある" => return None,
+            Form::Potential => match class {
+                VerbClass::Ichidan => format!("{}られる", stem),
+                VerbClass::Godan => format!("{}{}る", stem, kana::shift(last, 3)?),
+                VerbClass::Suru => format!("{}できる", verb.strip_suffix("する")?),
+                VerbClass::Kuru => format!("{}られる", kuru(verb, 'こ')?),
+            },
+            Form::Volitional => match class {
+                VerbClass::Ichidan => format!("{}よう", stem),
+                VerbClass::Godan => format!("{}{}う", stem, kana::shift(last, 4)?),
+                VerbClass::Suru => format!("{}しよう", verb.strip_suffix("する")?),
+                VerbClass::Kuru => format!("{}よう", kuru(verb, 'こ')?),
+            },
+        };
+        Some(conjugated)
+    }
+
+
+    // The た form, or the て form when te is true. Godan verbs change their
+    // sound here (のむ -> のんだ, かく -> かいた).
+    fn ta_form(verb: &str, class: VerbClass, te: bool) -> Option<String> {
+        let (ta, da) = if te { ("て", "で") } else { ("た", "だ") };
+        let mut chars = verb.chars();
+        let last = chars.next_back()?;
+        let stem = chars.as_str();
+
+        let conjugated = match class {
+            VerbClass::Ichidan => format!("{}{}", stem, ta),
+            VerbClass::Suru => format!("{}し{}", verb.strip_suffix("する")?, ta),
+            VerbClass::Kuru => format!("{}{}", kuru(verb, 'き')?, ta),
+            // いく is the one exception.
+            VerbClass::Godan if verb.ends_with("いく") || verb.ends_with("行く") => {
+                format!("{}っ{}", stem, ta)
+            }
+            VerbClass::Godan => match last {
+                'う' | 'つ' | 'る' => format!("{}っ{}", stem, ta),
+                'む' | 'ぶ' | 'ぬ' => format!("{}ん{}", stem, da),
+                'く' => format!("{}い{}", stem, ta),
+                'ぐ' => format!("{}い{}", stem, da),
+                'す' => format!("{}し{}", stem, ta),
+                _ => return None,
+            },
+        };
+        Some(conjugated)
+    }
+
+
+    // くる changes its first sound (きます, こない). Written with a kanji
+    // (来る), the kanji just stays.
+    fn kuru(verb: &str, sound: char) -> Option<String> {
+        if let Some(prefix) = verb.strip_suffix("来る") {
+            return Some(format!("{}来", prefix))
+        }
+        let prefix = verb.strip_suffix("くる")?;
+        Some(format!("{}{}", prefix, sound))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn forms() {
+            // The verb, its class, the form and what it should come out as.
+            let cases = [
+                ("のむ", VerbClass::Godan, Form::Past, Some("のんだ")),
+                ("のむ", VerbClass::Godan, Form::Negative, Some("のまない")),
+                ("のむ", VerbClass::Godan, Form::Polite, Some("のみます")),
+                ("かう", VerbClass::Godan, Form::Negative, Some("かわない")),
+                ("かう", VerbClass::Godan, Form::Te, Some("かって")),
+                ("いく", VerbClass::Godan, Form::Past, Some("いった")),
+                ("行く", VerbClass::Godan, Form::Te, Some("行って")),
+                ("かく", VerbClass::Godan, Form::Past, Some("かいた")),
+                ("はなす", VerbClass::Godan, Form::Potential, Some("はなせる")),
+                ("たべる", VerbClass::Ichidan, Form::Potential, Some("たべられる")),
+                ("たべる", VerbClass::Ichidan, Form::Volitional, Some("たべよう")),
+                ("くる", VerbClass::Kuru, Form::Negative, Some("こない")),
+                ("くる", VerbClass::Kuru, Form::Polite, Some("きます")),
+                ("来る", VerbClass::Kuru, Form::Past, Some("来た")),
+                ("する", VerbClass::Suru, Form::Potential, Some("できる")),
+                ("べんきょうする", VerbClass::Suru, Form::PoliteNegative, Some("べんきょうしません")),
+                ("ある", VerbClass::Godan, Form::Negative, Some("ない")),
+                ("ある", VerbClass::Godan, Form::PastNegative, Some("なかった")),
+                ("ある", VerbClass::Godan, Form::Past, Some("あった")),
+                ("ある", VerbClass::Godan, Form::Potential, None),
+            ];
+            for (verb, class, form, expected) in cases {
+                assert_eq!(conjugate(verb, class, form).as_deref(), expected, "{} {}", verb, form);
+            }
+        }
+
+        #[test]
+        fn classes() {
+            let cases = [
+                ("たべる", Some(VerbClass::Ichidan)),
+                ("みる", Some(VerbClass::Ichidan)),
+                ("かえる", Some(VerbClass::Godan)),
+                ("のむ", Some(VerbClass::Godan)),
+                ("べんきょうする", Some(VerbClass::Suru)),
+                ("くる", Some(VerbClass::Kuru)),
+                ("です", None),
+            ];
+            for (verb, class) in cases {
+                assert_eq!(guess_class(verb), class, "{}", verb);
+            }
+        }
+    }
+}
+
+
+// Guesses what type of word something is from how it looks. It's only a
+// guess, the user confirms it when they set the meaning.
+mod classify {
+    use std::collections::HashSet;
+
+    use crate::conjugate;
+    use crate::deinflect;
+    use crate::kana;
+    use crate::structures::{JapaneseWordType, VerbClass, WordType};
+
+
+    // Particles that show up on their own in the lessons.
+    const PARTICLES: [&str; 17] = [
+        "は", "が", "を", "に", "で", "と", "も", "へ", "の", "や", "か", "ね",
+        "よ", "から", "まで", "より", "けど",
+    ];
+
+    // Counters, on their own.
+    const COUNTERS: [&str; 9] = [
+        "がつ", "にち", "じ", "ふん", "ぷん", "ようび", "さい", "こ", "ほん",
+    ];
+
+    // Endings that make a word a noun, like あかいろ, げつようび and ろくがつ.
+    const NOUN_ENDINGS: [&str; 3] = ["いろ", "ようび", "がつ"];
+
+    // Words that are said on their own, like yes and no. Unlike the set
+    // phrases these have to match the whole word, since はい is also the
+    // start of はいる.
+    const INTERJECTIONS: [&str; 8] = [
+        "はい", "いいえ", "ええ", "うん", "ううん", "ああ", "さあ", "もしもし",
+    ];
+
+
+    // forms are the inflected versions of the word that were seen, if any.
+    pub fn suggest(word: &str, forms: &[String]) -> Option<WordType> {
+        if word.is_empty() {
+            return None
+        }
+        // Set phrases like すみません end in ます too, so these have to be
+        // checked before the verbs.
+        if deinflect::is_expression(word) {
+            return Some(WordType::Expression)
+        }
+        if INTERJECTIONS.contains(&word) {
+            return Some(WordType::Interjection)
+        }
+        if PARTICLES.contains(&word) {
+            return Some(WordType::Particle)
+        }
+        if COUNTERS.contains(&word) {
+            return Some(WordType::Counter)
+        }
+        if word == "です" || word == "ます" {
+            return Some(WordType::Auxiliary)
+        }
+        if NOUN_ENDINGS.iter().any(|e| word.ends_with(e)) || word == "いろ" {
+            return Some(WordType::Noun)
+        }
+
+        // Loan words are written in katakana, and they're almost always
+        // nouns.
+        if word.chars().all(|c| JapaneseWordType::of(c) == JapaneseWordType::Katakana) {
+            return Some(WordType::Noun)
+        }
+
+        // Verbs. Either the word is still in the ます form, or it was seen
+        // inflected and looks like a dictionary form verb.
+        if deinflect::is_polite(word) {
+            let class = deinflect::deinflect(word, &HashSet::new())
+                .and_then(|l| conjugate::guess_class(&l))
+                .unwrap_or(VerbClass::Godan);
+            return Some(WordType::from_verb_class(class))
+        }
+        let seen_inflected = forms.iter()
+            .any(|f| deinflect::is_polite(f) || f.ends_with("ない"));
+        if seen_inflected || word.ends_with("する") {
+            if let Some(c) = conjugate::guess_class(word) {
+                return Some(WordType::from_verb_class(c))
+            }
+        }
+
+        // Seen as たかくない or たかかった, so it's an い adjective for sure.
+        let adjective_forms = ["くない", "かった", "くて", "くありません"];
+        if word.ends_with('い') && forms.iter().any(|f| adjective_forms.iter().any(|e| f.ends_with(e))) {
+            return Some(WordType::adjective(word)).filter(|t| *t == WordType::IAdjective)
+        }
+
+        // Otherwise only guess an い adjective if it's spelled like one.
+        if looks_like_i_adjective(word) {
+            return Some(WordType::adjective(word)).filter(|t| *t == WordType::IAdjective)
+        }
+
+        None
+    }
+
+
+    // い adjectives end in あい, いい, うい or おい (たかい, おいしい, さむい,
+    // おおい), or kanji and い (高い). Words read the chinese way, like
+    // きょうだい or しょうかい, end in い too but are nouns, and those
+    // usually have a small ゃ, ゅ or ょ in them or end in だい.
+    fn looks_like_i_adjective(word: &str) -> bool {
+        let mut chars = word.chars().rev();
+        if chars.next() != Some('い') {
+            return false
+        }
+        let before = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+        let kanji = JapaneseWordType::of(before) == JapaneseWordType::Kanji;
+        let column = kana::column(before);
+        if !kanji && !matches!(column, Some(0) | Some(1) | Some(2) | Some(4)) {
+            return false
+        }
+        !word.ends_with("だい") && !word.contains(['ゃ', 'ゅ', 'ょ'])
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn suggestions() {
+            let cases = [
+                ("ごめんなさい", Some(WordType::Expression)),
+                ("ください", Some(WordType::Expression)),
+                ("すみません", Some(WordType::Expression)),
+                ("はい", Some(WordType::Interjection)),
+                ("いいえ", Some(WordType::Interjection)),
+                ("は", Some(WordType::Particle)),
+                ("げつようび", Some(WordType::Noun)),
+                ("コーヒー", Some(WordType::Noun)),
+                ("かいませんでした", Some(WordType::GodanVerb)),
+                ("たべました", Some(WordType::IchidanVerb)),
+                ("のみます", Some(WordType::GodanVerb)),
+                ("べんきょうする", Some(WordType::IrregularVerb)),
+                ("たかい", Some(WordType::IAdjective)),
+                ("おいしい", Some(WordType::IAdjective)),
+                ("さむい", Some(WordType::IAdjective)),
+                ("高い", Some(WordType::IAdjective)),
+                ("きょうだい", None),
+                ("しゅくだい", None),
+                ("しょうかい", None),
+                ("きれい", None),
+                ("せんせい", None),
+                ("わたし", None),
+            ];
+            for (word, expected) in cases {
+                assert_eq!(suggest(word, &[]), expected, "{}", word);
+            }
+        }
+
+        #[test]
+        fn seen_forms() {
+            let forms = ["たのしくない".to_string()];
+            assert_eq!(suggest("たのしい", &forms), Some(WordType::IAdjective));
+            let forms = ["のまない".to_string()];
+            assert_eq!(suggest("のむ", &forms), Some(WordType::GodanVerb));
+        }
+    }
+}
+
+
+// Converts between kana and romaji. Kana can be spelled out in Hepburn
+// (shi, tsu, ja) or Kunrei-shiki (si, tu, zya), and either one can be read
+// back in.
+mod romaji {
+    use crate::kana;
+
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum System {
+        Hepburn,
+        Kunrei,
+    }
+
+    impl System {
+        pub fn from_arg(arg: &str) -> Option<System> {
+            match arg {
+                "hepburn" => Some(System::Hepburn),
+                "kunrei" => Some(System::Kunrei),
+                _ => None,
+            }
+        }
+    }
+
+
+    // Every kana on its own, in Hepburn. Small ゃ, ゅ and ょ only show up
+    // here when they don't follow an い column kana.
+    const KANA: [(char, &str); 84] = [
+        ('あ', "a"), ('い', "i"), ('う', "u"), ('え', "e"), ('お', "o"),
+        ('か', "ka"), ('き', "ki"), ('く', "ku"), ('け', "ke"), ('こ', "ko"),
+        ('が', "ga"), ('ぎ', "gi"), ('ぐ', "gu"), ('げ', "ge"), ('ご', "go"),
+        ('さ', "sa"), ('し', "shi"), ('す', "su"), ('せ', "se"), ('そ', "so"),
+        ('ざ', "za"), ('じ', "ji"), ('ず', "zu"), ('ぜ', "ze"), ('ぞ', "zo"),
+        ('た', "ta"), ('ち', "chi"), ('つ', "tsu"), ('て', "te"), ('と', "to"),
+        ('だ', "da"), ('ぢ', "ji"), ('づ', "zu"), ('で', "de"), ('ど', "do"),
+        ('な', "na"), ('に', "ni"), ('ぬ', "nu"), ('ね', "ne"), ('の', "no"),
+        ('は', "ha"), ('ひ', "hi"), ('ふ', "fu"), ('へ', "he"), ('ほ', "ho"),
+        ('ば', "ba"), ('び', "bi"), ('ぶ', "bu"), ('べ', "be"), ('ぼ', "bo"),
+        ('ぱ', "pa"), ('ぴ', "pi"), ('ぷ', "pu"), ('ぺ', "pe"), ('ぽ', "po"),
+        ('ま', "ma"), ('み', "mi"), ('む', "mu"), ('め', "me"), ('も', "mo"),
+        ('や', "ya"), ('ゆ', "yu"), ('よ', "yo"),
+        ('ら', "ra"), ('り', "ri"), ('る', "ru"), ('れ', "re"), ('ろ', "ro"),
+        ('わ', "wa"), ('ゐ', "i"), ('ゑ', "e"), ('を', "o"), ('ゔ', "vu"),
+        ('ぁ', "a"), ('ぃ', "i"), ('ぅ', "u"), ('ぇ', "e"), ('ぉ', "o"),
+        ('ゃ', "ya"), ('ゅ', "yu"), ('ょ', "yo"), ('ゎ', "wa"),
+        ('ん', "n"), ('っ', ""),
+    ];
+
+    // What an い column kana turns into in front of a small ゃ, ゅ or ょ,
+    // like き -> ky (きゃ = kya).
+    const Y_STEMS: [(char, &str); 13] = [
+        ('き', "ky"), ('ぎ', "gy"), ('し', "sh"), ('じ', "j"), ('ち', "ch"),
+        ('ぢ', "j"), ('に', "ny"), ('ひ', "hy"), ('び', "by"), ('ぴ', "py"),
+        ('み', "my"), ('り', "ry"), ('ゔ', "vy"),
+    ];
+
+    // Same thing for the small vowels katakana uses for foreign sounds, like
+    // ふ -> f (ファ = fa) or て -> t (ティ = ti).
+    const VOWEL_STEMS: [(char, &str); 9] = [
+        ('ふ', "f"), ('て', "t"), ('で', "d"), ('う', "w"), ('ゔ', "v"),
+        ('し', "sh"), ('じ', "j"), ('ち', "ch"), ('と', "t"),
+    ];
+
+    // The spellings Kunrei-shiki does differently. Only used when reading
+    // romaji in, since Hepburn gets the table above.
+    const KUNREI: [(&str, &str); 17] = [
+        ("si", "し"), ("ti", "ち"), ("tu", "つ"), ("hu", "ふ"), ("zi", "じ"),
+        ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+        ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+        ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"),
+        // Not Kunrei, but it's how ぢ, づ and を get typed.
+        ("di", "ぢ"), ("du", "づ"), ("wo", "を"),
+    ];
+
+
+    // Turns hiragana or katakana into romaji. Anything that isn't kana is
+    // left as it is. Long vowels are spelled out (コーヒー = koohii) so the
+    // romaji can be typed back in.
+    pub fn to_romaji(s: &str, system: System) -> String {
+        let chars: Vec<char> = s.chars().map(kana::to_hiragana).collect();
+        let mut out = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                // Small tsu doubles the consonant after it (きって = kitte),
+                // except ch, which becomes tch (まっちゃ = matcha).
+                'っ' => {
+                    let next = syllable(&chars[i + 1..], system).map(|(r, _)| r);
+                    match next.as_deref().and_then(|r| r.chars().next()) {
+                        Some('c') => out.push('t'),
+                        Some(n) if !"aiueon".contains(n) => out.push(n),
+                        _ => (),
+                    }
+                    i += 1;
+                }
+                // The long vowel mark repeats the vowel before it.
+                'ー' => {
+                    if let Some(v) = out.chars().last().filter(|v| "aiueo".contains(*v)) {
+                        out.push(v);
+                    }
+                    i += 1;
+                }
+                // ん before a vowel or y gets an apostrophe, so きんえん
+                // (kin'en) doesn't read as きねん (kinen).
+                'ん' => {
+                    out.push('n');
+                    let next = syllable(&chars[i + 1..], system).map(|(r, _)| r);
+                    if next.as_deref().and_then(|r| r.chars().next()).is_some_and(|n| "aiueoy".contains(n)) {
+                        out.push('\'');
+                    }
+                    i += 1;
+                }
+                _ => match syllable(&chars[i..], system) {
+                    Some((r, len)) => {
+                        out.push_str(&r);
+                        i += len;
+                    }
+                    None => {
+                        out.push(c);
+                        i += 1;
+                    }
+                },
+            }
+        }
+        out
+    }
+
+
+    // The romaji for the kana at the front of chars, and how many chars it
+    // used up.
+    fn syllable(chars: &[char], system: System) -> Option<(String, usize)> {
+        let first = *chars.first()?;
+        let mut found = None;
+        if let Some(small) = chars.get(1) {
+            let vowel = match small {
+                'ゃ' => Some("a"),
+                'ゅ' => Some("u"),
+                'ょ' => Some("o"),
+                _ => None,
+            };
+            if let Some(v) = vowel {
+                if let Some((_, stem)) = Y_STEMS.iter().find(|(k, _)| *k == first) {
+                    found = Some((format!("{}{}", stem, v), 2));
+                }
+            }
+            if "ぁぃぅぇぉ".contains(*small) {
+                if let Some((_, stem)) = VOWEL_STEMS.iter().find(|(k, _)| *k == first) {
+                    let v = KANA.iter().find(|(k, _)| k == small).map(|(_, r)| *r)?;
+                    found = Some((format!("{}{}", stem, v), 2));
+                }
+            }
+        }
+        let (r, len) = found.or_else(|| {
+            KANA.iter().find(|(k, _)| *k == first).map(|(_, r)| (r.to_string(), 1))
+        })?;
+
+        match system {
+            System::Hepburn => Some((r, len)),
+            System::Kunrei => Some((kunrei(&r), len)),
+        }
+    }
+
+
+    // Respells one Hepburn syllable in Kunrei-shiki.
+    fn kunrei(r: &str) -> String {
+        match r {
+            "shi" => return String::from("si"),
+            "chi" => return String::from("ti"),
+            "tsu" => return String::from("tu"),
+            "fu" => return String::from("hu"),
+            "ji" => return String::from("zi"),
+            _ => (),
+        }
+        for (hepburn, kunrei) in [("sh", "sy"), ("ch", "ty"), ("j", "zy")] {
+            if let Some(v) = r.strip_prefix(hepburn) {
+                return format!("{}{}", kunrei, v)
+            }
+        }
+        r.to_string()
+    }
+
+
+    // Turns romaji (Hepburn or Kunrei) into hiragana, or katakana if
+    // katakana is set. Long vowels can be typed with a macron or circumflex
+    // (tōkyō), or with a - for katakana (ko-hi-). Anything that can't be read
+    // is left as it is.
+    pub fn to_kana(s: &str, katakana: bool) -> String {
+        let mut chars: Vec<char> = Vec::new();
+        for c in s.to_lowercase().chars() {
+            let long = match c {
+                'ā' | 'â' => Some(('a', 'a')),
+                'ī' | 'î' => Some(('i', 'i')),
+                'ū' | 'û' => Some(('u', 'u')),
+                'ē' | 'ê' => Some(('e', 'e')),
+                'ō' | 'ô' => Some(('o', 'u')),
+                _ => None,
+            };
+            match long {
+                Some((v, _)) if katakana => chars.extend([v, '-']),
+                Some((v, after)) => chars.extend([v, after]),
+                None => chars.push(c),
+            }
+        }
+
+        let table = table();
+        let mut out = String::new();
+        // The vowel the last syllable ended in, for long vowels in katakana.
+        let mut last_vowel = None;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            let is_vowel = |c: Option<char>| c.is_some_and(|c| "aiueoy".contains(c));
+            let vowel_before = last_vowel.take();
+
+            // ん. A lone n before a consonant (konnichiwa), n' and nn all
+            // work, as long as the nn isn't the start of a な row kana.
+            if c == 'n' && !is_vowel(next) {
+                out.push('ん');
+                let doubled = next == Some('\'') || (next == Some('n') && !is_vowel(chars.get(i + 2).copied()));
+                i += if doubled { 2 } else { 1 };
+                continue;
+            }
+
+            // A doubled consonant is a small tsu (kitte), and so is the t
+            // in tch (matcha).
+            let doubled = next == Some(c) || (c == 't' && next == Some('c'));
+            if c.is_ascii_lowercase() && !is_vowel(Some(c)) && doubled {
+                out.push('っ');
+                i += 1;
+                continue;
+            }
+
+            if c == '-' {
+                out.push('ー');
+                i += 1;
+                continue;
+            }
+
+            // Longest match first, so "sha" wins over "s".
+            let matched = (1..=3).rev().find_map(|len| {
+                let piece: String = chars.get(i..i + len)?.iter().collect();
+                table.iter().find(|(r, _)| *r == piece).map(|(_, k)| (k.clone(), len))
+            });
+            match matched {
+                // In katakana a doubled vowel is a long vowel, so koohii is
+                // コーヒー like to_romaji spells it.
+                Some(_) if katakana && vowel_before == Some(c) => {
+                    out.push('ー');
+                    last_vowel = vowel_before;
+                    i += 1;
+                }
+                Some((k, len)) => {
+                    out.push_str(&k);
+                    last_vowel = Some(chars[i + len - 1]).filter(|v| "aiueo".contains(*v));
+                    i += len;
+                }
+                None => {
+                    out.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        if katakana {
+            out.chars().map(kana::to_katakana).collect()
+        } else {
+            out
+        }
+    }
+
+
+    // Converts what was typed at a prompt. Each run of romaji becomes
+    // hiragana, or katakana if it starts with a capital letter (Koohii or
+    // KO-HI- -> コーヒー). Anything else, like kana that was already there,
+    // stays the same.
+    pub fn convert_input(line: &str) -> String {
+        let mut out = String::new();
+        let mut run = String::new();
+        for c in line.chars() {
+            let joins = c.is_ascii_alphabetic() || "'āīūēōâîûêô".contains(c) || (c == '-' && !run.is_empty());
+            if joins {
+                run.push(c);
+                continue;
+            }
+            out.push_str(&convert_run(&run));
+            run.clear();
+            out.push(c);
+        }
+        out.push_str(&convert_run(&run));
+        out
+    }
+
+    fn convert_run(run: &str) -> String {
+        let katakana = run.chars().next().is_some_and(|c| c.is_uppercase());
+        to_kana(run, katakana)
+    }
+
+
+    // Whether s looks like romaji, as opposed to kana or kanji.
+    pub fn is_romaji(s: &str) -> bool {
+        s.chars().any(|c| c.is_ascii_alphabetic())
+            && s.chars().all(|c| c.is_ascii_alphabetic() || "'-āīūēōâîûêô".contains(c))
+    }
+
+
+    // Every romaji spelling that can be read, and the kana it's read as.
+    // When two kana are spelled the same (じ and ぢ are both ji), the first
+    // one wins.
+    fn table() -> Vec<(String, String)> {
+        let mut table: Vec<(String, String)> = Vec::new();
+        for (k, r) in KANA {
+            if !"ゐゑをんっぁぃぅぇぉゃゅょゎ".contains(k) {
+                table.push((r.to_string(), k.to_string()));
+            }
+        }
+        for (k, stem) in Y_STEMS {
+            for (small, v) in [('ゃ', "a"), ('ゅ', "u"), ('ょ', "o")] {
+                table.push((format!("{}{}", stem, v), format!("{}{}", k, small)));
+            }
+        }
+        for (r, k) in KUNREI {
+            table.push((r.to_string(), k.to_string()));
+        }
+        for (k, stem) in VOWEL_STEMS {
+            for (small, v) in [('ぁ', "a"), ('ぃ', "i"), ('ぅ', "u"), ('ぇ', "e"), ('ぉ', "o")] {
+                table.push((format!("{}{}", stem, v), format!("{}{}", k, small)));
+            }
+        }
+        table
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn round_trips() {
+            // The kana, then how it's spelled in Hepburn and Kunrei.
+            let cases = [
+                ("たべる", "taberu", "taberu"),
+                ("しんぶん", "shinbun", "sinbun"),
+                ("ちかてつ", "chikatetsu", "tikatetu"),
+                ("ふじさん", "fujisan", "huzisan"),
+                ("しゃしん", "shashin", "syasin"),
+                ("ちょっと", "chotto", "tyotto"),
+                ("じゅぎょう", "jugyou", "zyugyou"),
+                ("きって", "kitte", "kitte"),
+                ("まっちゃ", "matcha", "mattya"),
+                ("きんえん", "kin'en", "kin'en"),
+                ("こんやく", "kon'yaku", "kon'yaku"),
+                ("きねん", "kinen", "kinen"),
+                ("とうきょう", "toukyou", "toukyou"),
+                ("おかあさん", "okaasan", "okaasan"),
+            ];
+            for (kana, hepburn, kunrei) in cases {
+                assert_eq!(to_romaji(kana, System::Hepburn), hepburn);
+                assert_eq!(to_romaji(kana, System::Kunrei), kunrei);
+                assert_eq!(to_kana(hepburn, false), kana, "{}", hepburn);
+                assert_eq!(to_kana(kunrei, false), kana, "{}", kunrei);
+            }
+        }
+
+        #[test]
+        fn katakana() {
+            let cases = [
+                ("コーヒー", "koohii"),
+                ("パーティー", "paatii"),
+                ("ファイル", "fairu"),
+                ("ベッド", "beddo"),
+            ];
+            for (kana, romaji) in cases {
+                assert_eq!(to_romaji(kana, System::Hepburn), romaji);
+            }
+            assert_eq!(to_kana("koohii", true), "コーヒー");
+            assert_eq!(to_kana("ko-hi-", true), "コーヒー");
+            assert_eq!(to_kana("fairu", true), "ファイル");
+            assert_eq!(to_kana("beddo", true), "ベッド");
+        }
+
+        #[test]
+        fn long_vowels_and_n() {
+            assert_eq!(to_kana("tōkyō", false), "とうきょう");
+            assert_eq!(to_kana("tôkyô", false), "とうきょう");
+            assert_eq!(to_kana("tōkyō", true), "トーキョー");
+            assert_eq!(to_kana("konnichiwa", false), "こんにちわ");
+            assert_eq!(to_kana("onna", false), "おんな");
+            assert_eq!(to_kana("hon", false), "ほん");
+            assert_eq!(to_kana("kinnen", false), "きんねん");
+        }
+
+        #[test]
+        fn input() {
+            assert_eq!(convert_input("taberu"), "たべる");
+            assert_eq!(convert_input("Koohii"), "コーヒー");
+            assert_eq!(convert_input("KO-HI- wo nomu"), "コーヒー を のむ");
+            assert_eq!(convert_input("たべる"), "たべる");
+        }
+    }
+}
+
+
+// Keeps old word files working. The word file is saved as
+// {"version": N, "words": [...], "sentences": [...]}, and files from older
+// versions get upgraded one version at a time when they're loaded. Anything
+// that's just a list of words is version 0, which is how every version
+// before the envelope saved it.
+//
+// To change the format, bump VERSION and add a step to STEPS that takes
+// the words from the version before it.
+mod migrate {
+    use serde::Serialize;
+    use serde_json::Value;
+    use crate::structures::{JPWord, Sense, Sentence, WordType};
+
+
+    pub const VERSION: u64 = 2;
+
+    // What each step does, and the step itself. STEPS[0] goes from version
+    // 0 to 1, and so on. Each step returns how many words it changed.
+    type Step = fn(&mut Vec<Value>) -> usize;
+    const STEPS: [(&str, Step); 2] = [
+        ("Switched word types to japanese parts of speech", word_types),
+        ("Moved definitions into the list of meanings", senses),
+    ];
+
+
+    // What happened when a file was upgraded.
+    pub struct Report {
+        pub from: u64,
+        pub changes: Vec<String>,
+    }
+
+
+    // Everything in the word file.
+    pub struct Contents {
+        pub words: Vec<JPWord>,
+        pub sentences: Vec<Sentence>,
+    }
+
+
+    // Reads the word file, upgrading it if it's old. Errors if it isn't a
+    // word file, or if it was saved by a newer version than this one.
+    pub fn load(f_words: &str) -> Result<(Contents, Report), String> {
+        let value: Value = serde_json::from_str(f_words)
+            .map_err(|e| format!("Could not parse json from file: {}", e))?;
+
+        let (from, mut words, sentences) = match value {
+            Value::Array(words) => (0, words, Vec::new()),
+            Value::Object(mut o) => {
+                let version = o.get("version").and_then(Value::as_u64)
+                    .ok_or("The word file doesn't say what version it is")?;
+                let sentences = match o.remove("sentences") {
+                    Some(Value::Array(sentences)) => sentences,
+                    _ => Vec::new(),
+                };
+                match o.remove("words") {
+                    Some(Value::Array(words)) => (version, words, sentences),
+                    _ => return Err(String::from("The word file doesn't have a list of words")),
+                }
+            }
+            _ => return Err(String::from("That doesn't look like a word file")),
+        };
+
+        let report = upgrade(&mut words, from)?;
+        let words = serde_json::from_value(Value::Array(words))
+            .map_err(|e| format!("Could not read the words: {}", e))?;
+        let sentences = serde_json::from_value(Value::Array(sentences))
+            .map_err(|e| format!("Could not read the sentences: {}", e))?;
+        Ok((Contents { words, sentences }, report))
+    }
+
+
+    // Runs every step after version from on the words. This is the part
+    // that doesn't care how the words are stored.
+    pub fn upgrade(words: &mut Vec<Value>, from: u64) -> Result<Report, String> {
+        if from > VERSION {
+            return Err(format!(
+                "The word list is version {}, but this only knows up to version {}. Try updating sear_stone.",
+                from, VERSION,
+            ))
+        }
+
+        let mut changes: Vec<String> = Vec::new();
+        for (i, (what, step)) in STEPS.iter().enumerate().skip(from as usize) {
+            let changed = step(words);
+            changes.push(format!("{} -> {}: {} ({} words)", i, i + 1, what, changed));
+        }
+        Ok(Report { from, changes })
+    }
+
+
+    #[derive(Serialize)]
+    struct Envelope<'a> {
+        version: u64,
+        words: &'a Vec<JPWord>,
+        sentences: &'a Vec<Sentence>,
+    }
+
+    // The word file, ready to be written.
+    pub fn to_string(words: &Vec<JPWord>, sentences: &Vec<Sentence>) -> String {
+        serde_json::to_string(&Envelope { version: VERSION, words, sentences })
+            .expect("Could not parse into JSON before writing")
+    }
+
+
+    // 0 -> 1: The english word types (Noun, Verb, Adjective, ...) became
+    // japanese ones, see WordType::from_legacy.
+    fn word_types(words: &mut Vec<Value>) -> usize {
+        let mut changed = 0;
+        for w in words {
+            let word = w["word"].as_str().unwrap_or_default().to_string();
+            if let Some(old) = w["word_type"].as_str() {
+                if let Some(new) = WordType::from_legacy(old, &word) {
+                    let new = serde_json::to_value(new).expect("Could not parse into JSON");
+                    if w["word_type"] != new {
+                        w["word_type"] = new;
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+
+    // 1 -> 2: Words had one definition, now they have a list of senses. The
+    // definition becomes the first one.
+    fn senses(words: &mut Vec<Value>) -> usize {
+        let mut changed = 0;
+        for w in words {
+            let obj = match w.as_object_mut() {
+                Some(o) => o,
+                None => continue,
+            };
+            if let Some(def) = obj.remove("definition") {
+                let def = def.as_str().map(str::trim).unwrap_or_default();
+                if !def.is_empty() && !obj.contains_key("senses") {
+                    let mut sense = Sense::new(def);
+                    sense.word_type = serde_json::from_value(obj["word_type"].clone())
+                        .unwrap_or(None);
+                    obj.insert(String::from("senses"), serde_json::to_value(vec![sense])
+                        .expect("Could not parse into JSON"));
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn bare_array() {
+            let f = r#"[
+                {"word":"たべる","word_type":"Verb","definition":"to eat"},
+                {"word":"勉強","word_type":"Verb","definition":" study "},
+                {"word":"あかい","word_type":"Adjective","definition":""}
+            ]"#;
+            let (c, report) = load(f).unwrap();
+            assert_eq!(report.from, 0);
+            assert_eq!(report.changes.len(), 2);
+
+            let types: Vec<Option<WordType>> = c.words.iter().map(|w| w.word_type).collect();
+            assert_eq!(types, [Some(WordType::IchidanVerb), Some(WordType::Noun), Some(WordType::IAdjective)]);
+            assert_eq!(c.words[0].senses[0].gloss, "to eat");
+            assert_eq!(c.words[0].senses[0].word_type, Some(WordType::IchidanVerb));
+            assert_eq!(c.words[1].senses[0].gloss, "study");
+            // Empty definitions don't become a meaning.
+            assert!(c.words[2].senses.is_empty());
+            assert!(c.sentences.is_empty());
+        }
+
+        #[test]
+        fn envelope() {
+            let f = r#"{"version":1,"words":[{"word":"のむ","word_type":"GodanVerb","definition":"to drink"}]}"#;
+            let (c, report) = load(f).unwrap();
+            assert_eq!(report.from, 1);
+            assert_eq!(report.changes.len(), 1);
+            assert!(report.changes[0].starts_with("1 -> 2"));
+            assert_eq!(c.words[0].word_type, Some(WordType::GodanVerb));
+            assert_eq!(c.words[0].senses[0].gloss, "to drink");
+            assert!(c.sentences.is_empty());
+        }
+
+        #[test]
+        fn current() {
+            let mut word = JPWord::simple_new(String::from("みず"));
+            word.senses.push(Sense::new("water"));
+            let sentence = Sentence {
+                id: 7,
+                text: String::from("みずをのみます。"),
+                translation: None,
+                source: String::from("lesson.txt"),
+            };
+            let f = to_string(&vec![word.clone()], &vec![sentence.clone()]);
+
+            let (c, report) = load(&f).unwrap();
+            assert_eq!(report.from, VERSION);
+            assert!(report.changes.is_empty());
+            assert_eq!(c.words, [word]);
+            assert_eq!(c.sentences, [sentence]);
+        }
+
+        #[test]
+        fn no_sentences() {
+            // Lists saved before there were example sentences.
+            let f = r#"{"version":2,"words":[{"word":"みず","word_type":null}]}"#;
+            let (c, report) = load(f).unwrap();
+            assert!(report.changes.is_empty());
+            assert_eq!(c.words.len(), 1);
+            assert!(c.sentences.is_empty());
+        }
+
+        #[test]
+        fn newer_version() {
+            let f = format!(r#"{{"version":{},"words":[]}}"#, VERSION + 1);
+            let err = load(&f).err().unwrap();
+            assert!(err.contains(&format!("version {}", VERSION + 1)), "{}", err);
+        }
+
+        #[test]
+        fn not_a_word_file() {
+            assert!(load("not json").is_err());
+            assert!(load(r#""words""#).is_err());
+            assert!(load(r#"{"words":[]}"#).is_err());
+            assert!(load(r#"{"version":2}"#).is_err());
+        }
+    }
+}
+
+
+// Figures out where the word list is kept. The first one of these that's
+// set wins:
+//  1. --data <file>
+//  2. the SEAR_STONE_DATA environment variable
+//  3. "data" in the config file ($XDG_CONFIG_HOME/sear_stone/config.json)
+//  4. $XDG_DATA_HOME/sear_stone/word_list.json (~/.local/share/...)
+// The stop list is kept in the same folder as the words.
+// With --profile, that's the main word list, and the profile's own list is
+// in the profiles folder next to it (see the profile module).
+mod config {
+    use std::{env, fs, process};
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+    use serde::Deserialize;
+    use crate::profile;
+
+
+    const ENV_VAR: &str = "SEAR_STONE_DATA";
+    const WORD_FILE: &str = "word_list.json";
+    const DATABASE_FILE: &str = "word_list.sqlite";
+    const STOP_FILE: &str = "stop_list.json";
+
+    // The kinds of store, which are also the extensions of their files.
+    pub const JSON: &str = "json";
+    pub const SQLITE: &str = "sqlite";
+
+    // Where older versions kept the list, relative to wherever they were
+    // run from.
+    const LEGACY_FILE: &str = "data/word_list.json";
+
+    static WORDS: OnceLock<PathBuf> = OnceLock::new();
+    static MAIN_WORDS: OnceLock<PathBuf> = OnceLock::new();
+    static PROFILE: OnceLock<String> = OnceLock::new();
+
+
+    // What can go in the config file. Everything is optional.
+    #[derive(Deserialize, Default)]
+    pub struct Config {
+        #[serde(default)]
+        pub data: Option<String>,
+        // How many backups of the word list to keep.
+        #[serde(default)]
+        pub backups: Option<usize>,
+        // json or sqlite, see the store module.
+        #[serde(default)]
+        pub store: Option<String>,
+    }
+
+
+    // Works out where the word list is, and remembers it for the rest of
+    // the run. Has to be called before anything reads or writes the list.
+    pub fn init(flag: Option<&str>, profile_name: Option<&str>) {
+        let (file, picked) = resolve(flag);
+
+        // If the list hasn't been moved over from where older versions kept
+        // it, let the user know instead of quietly starting a new one.
+        if !picked && !file.exists() && Path::new(LEGACY_FILE).exists() {
+            eprintln!("Found a word list in ./{}, but sear_stone keeps it in {} now.", LEGACY_FILE, file.display());
+            eprintln!("Move it there, or use --data {} to keep using it.", LEGACY_FILE);
+        }
+        let _ = MAIN_WORDS.set(file.clone());
+
+        // A profile that doesn't share the main list has its own, with the
+        // same name, in its folder.
+        let file = match profile_name {
+            None => file,
+            Some(name) => {
+                let p = match profile::read(name) {
+                    Some(p) => p,
+                    None => {
+                        eprintln!("There's no profile called {}. Make it with profile create {}.", name, name);
+                        process::exit(1);
+                    }
+                };
+                let _ = PROFILE.set(name.to_string());
+                match (p.shared, file.file_name()) {
+                    (false, Some(f)) => profile::path(name).join(f),
+                    _ => file,
+                }
+            }
+        };
+
+        let _ = WORDS.set(file);
+    }
+
+
+    // The word file, and whether the user picked it (as opposed to the
+    // default).
+    fn resolve(flag: Option<&str>) -> (PathBuf, bool) {
+        if let Some(f) = flag {
+            return (PathBuf::from(f), true)
+        }
+        if let Some(f) = env::var_os(ENV_VAR).filter(|f| !f.is_empty()) {
+            return (PathBuf::from(f), true)
+        }
+        if let Some(f) = load().data {
+            // Relative paths in the config are relative to the config file.
+            let f = PathBuf::from(f);
+            let f = match config_file().as_deref().and_then(Path::parent) {
+                Some(dir) if f.is_relative() => dir.join(f),
+                _ => f,
+            };
+            return (f, true)
+        }
+        let name = match load().store.as_deref() {
+            Some(SQLITE) => DATABASE_FILE,
+            _ => WORD_FILE,
+        };
+        (data_home().join("sear_stone").join(name), false)
+    }
+
+
+    // Reads the config file. It's fine if there isn't one.
+    pub fn load() -> Config {
+        let file = match config_file() {
+            Some(f) => f,
+            None => return Config::default(),
+        };
+        match fs::read_to_string(&file) {
+            Ok(s) if !s.trim().is_empty() => match serde_json::from_str(&s) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Could not read the config file {}: {}", file.display(), e);
+                    process::exit(1);
+                }
+            },
+            _ => Config::default(),
+        }
+    }
+
+
+    pub fn config_file() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+            Some(d) => PathBuf::from(d),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("sear_stone").join("config.json"))
+    }
+
+
+    fn data_home() -> PathBuf {
+        if let Some(d) = env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+            return PathBuf::from(d)
+        }
+        match env::var_os("HOME") {
+            Some(h) => PathBuf::from(h).join(".local").join("share"),
+            // Nowhere better to put it.
+            None => PathBuf::from("."),
+        }
+    }
+
+
+    pub fn word_file() -> &'static Path {
+        WORDS.get().expect("config::init has to be called first")
+    }
+
+    // Which store the word list is kept in (see the store module). A file
+    // ending in .json or .sqlite is always that kind, so the store can't
+    // disagree with the file. Anything else goes by the config file.
+    pub fn store() -> String {
+        match word_file().extension().and_then(|e| e.to_str()) {
+            Some(JSON) => String::from(JSON),
+            Some(SQLITE) => String::from(SQLITE),
+            _ => load().store.unwrap_or_else(|| String::from(JSON)),
+        }
+    }
+
+    // The word list used without --profile. Profiles are kept next to it.
+    pub fn main_word_file() -> &'static Path {
+        MAIN_WORDS.get().expect("config::init has to be called first")
+    }
+
+    // The --profile being used, if there is one.
+    pub fn profile() -> Option<&'static str> {
+        PROFILE.get().map(String::as_str)
+    }
+
+    // The folder the word list is in.
+    pub fn data_dir() -> &'static Path {
+        match word_file().parent() {
+            Some(d) if !d.as_os_str().is_empty() => d,
+            _ => Path::new("."),
+        }
+    }
+
+    pub fn stop_file() -> PathBuf {
+        data_dir().join(STOP_FILE)
+    }
+}
+
+
+// Profiles let more than one person study on the same machine. Each one is a
+// folder in profiles/, next to the main word list, with a profile.json in it.
+// A normal profile has its own word list and stop list. A shared
+// one uses the main word list and only keeps its own review progress (in
+// progress.json), so everyone has the same words but reviews them on their
+// own schedule.
+mod profile {
+    use std::{fs, io};
+    use std::path::PathBuf;
+    use serde::{Deserialize, Serialize};
+    use crate::backup;
+    use crate::config;
+
+
+    const PROFILE_FILE: &str = "profile.json";
+    const PROGRESS_FILE: &str = "progress.json";
+
+
+    #[derive(Serialize, Deserialize, Default)]
+    pub struct Profile {
+        // Whether it uses the main word list.
+        #[serde(default)]
+        pub shared: bool,
+    }
+
+
+    pub fn dir() -> PathBuf {
+        match config::main_word_file().parent() {
+            Some(d) if !d.as_os_str().is_empty() => d.join("profiles"),
+            _ => PathBuf::from("profiles"),
+        }
+    }
+
+    pub fn path(name: &str) -> PathBuf {
+        dir().join(name)
+    }
+
+
+    // Names end up being folder names, so keep them simple.
+    pub fn valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    }
+
+
+    // The profile, or None if there isn't one with that name.
+    pub fn read(name: &str) -> Option<Profile> {
+        if !valid_name(name) {
+            return None
+        }
+        let contents = fs::read_to_string(path(name).join(PROFILE_FILE)).ok()?;
+        if contents.trim().is_empty() {
+            return Some(Profile::default())
+        }
+        serde_json::from_str(&contents).ok()
+    }
+
+
+    pub fn create(name: &str, profile: &Profile) -> io::Result<()> {
+        fs::create_dir_all(path(name))?;
+        let stringified = serde_json::to_string(profile)
+            .expect("Could not parse into JSON before writing");
+        backup::write_atomic(&path(name).join(PROFILE_FILE), stringified)
+    }
+
+
+    // Every profile, sorted by name.
+    pub fn list() -> Vec<(String, Profile)> {
+        let entries = match fs::read_dir(dir()) {
+            Ok(e) => e,
+            Err(_) => return Vec::new(),
+        };
+        let mut profiles: Vec<(String, Profile)> = entries
+            .filter_map(|e| e.ok()?.file_name().into_string().ok())
+            .filter_map(|n| read(&n).map(|p| (n, p)))
+            .collect();
+        profiles.sort_by(|a, b| a.0.cmp(&b.0));
+        profiles
+    }
+
+
+    // Copies the files in a profile over to a new one. Its backups stay
+    // behind, and so do lock and temp files (they start with a dot).
+    pub fn copy(from: &str, to: &str) -> io::Result<()> {
+        fs::create_dir_all(path(to))?;
+        for entry in fs::read_dir(path(from))? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if !entry.file_type()?.is_file() || name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            fs::copy(entry.path(), path(to).join(&name))?;
+        }
+        Ok(())
+    }
+
+
+    pub fn delete(name: &str) -> io::Result<()> {
+        fs::remove_dir_all(path(name))
+    }
+
+
+    // Where the --profile being used keeps its review progress, if it
+    // shares the main word list.
+    pub fn progress_file() -> Option<PathBuf> {
+        let name = config::profile()?;
+        read(name)
+            .filter(|p| p.shared)
+            .map(|_| path(name).join(PROGRESS_FILE))
+    }
+}
+
+
+// Saving the word list safely. Saves go to a temp file that's renamed over
+// the real one, so a crash halfway through can't leave a half written list.
+// Before each save, the old list is copied into a backups folder next to it,
+// and only the newest few copies are kept.
+mod backup {
+    use std::{fs, io};
+    use std::path::{Path, PathBuf};
+    use crate::config;
+    use crate::lock;
+
+
+    // How many backups to keep, unless the config says otherwise.
+    const KEEP: usize = 10;
+    const PREFIX: &str = "word_list-";
+
+
+    pub fn dir() -> PathBuf {
+        config::data_dir().join("backups")
+    }
+
+
+    // Writes contents to path all at once. Either the old file or the new
+    // one is there afterwards, never part of one.
+    pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("data");
+        let tmp = path.with_file_name(format!(".{}.tmp", name));
+
+        let mut file = fs::File::create(&tmp)?;
+        io::Write::write_all(&mut file, contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+
+    // Saves the word list, backing up the one that's there first. If it's
+    // already exactly that, nothing is written or backed up.
+    pub fn save_words(contents: &str) -> io::Result<()> {
+        let _lock = lock::acquire();
+        if fs::read_to_string(config::word_file()).is_ok_and(|now| now == contents) {
+            lock::saved(contents);
+            return Ok(())
+        }
+        backup_current()?;
+        write_atomic(config::word_file(), contents)?;
+        lock::saved(contents);
+        Ok(())
+    }
+
+
+    // Backups of a JSON list end in .json and backups of a database end in
+    // .sqlite, whatever the word list itself is called.
+    fn extension() -> String {
+        config::store()
+    }
+
+
+    // Copies the current word list into the backups folder, and deletes the
+    // oldest backups if there are too many.
+    pub fn backup_current() -> io::Result<()> {
+        let current = config::word_file();
+        if fs::metadata(current).map(|m| m.len() == 0).unwrap_or(true) {
+            return Ok(())
+        }
+
+        fs::create_dir_all(dir())?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+        fs::copy(current, dir().join(format!("{}{}.{}", PREFIX, stamp, extension())))?;
+
+        let keep = config::load().backups.unwrap_or(KEEP);
+        for old in list().into_iter().skip(keep) {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+
+    // Every backup of the kind of list being used, newest first. A JSON
+    // backup can't be put back as a database, or the other way around.
+    pub fn list() -> Vec<PathBuf> {
+        let entries = match fs::read_dir(dir()) {
+            Ok(e) => e,
+            Err(_) => return Vec::new(),
+        };
+        let mut backups: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(PREFIX)))
+            .filter(|p| same_kind(p))
+            .collect();
+        // The timestamps sort the same way the names do.
+        backups.sort();
+        backups.reverse();
+        backups
+    }
+
+
+    // When a backup was made, from its name.
+    pub fn made_at(backup: &Path) -> Option<chrono::NaiveDateTime> {
+        let name = backup.file_stem()?.to_str()?.strip_prefix(PREFIX)?;
+        chrono::NaiveDateTime::parse_from_str(name, "%Y%m%d-%H%M%S%.3f").ok()
+    }
+
+
+    fn same_kind(backup: &Path) -> bool {
+        backup.extension().and_then(|e| e.to_str()) == Some(extension().as_str())
+    }
+
+
+    // Puts a backup back in place of the word list. The list that was there
+    // gets backed up first, so a restore can be undone.
+    pub fn restore(backup: &Path) -> io::Result<()> {
+        if !same_kind(backup) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't a backup of a {} word list", backup.display(), extension()),
+            ))
+        }
+        let _lock = lock::acquire();
+        let contents = fs::read(backup)?;
+        backup_current()?;
+        write_atomic(config::word_file(), contents)
+    }
+}
+
+
+// Keeps two copies of sear_stone from stepping on each other. The word list
+// is locked while it's read and while it's saved, but not in between, so a
+// quiz waiting on the user doesn't hold anything up. Since something else
+// can save the list in between, saving checks whether the file is still what
+// was read. If it isn't, the store reads it again and only puts this run's
+// changes on top (see the store module).
+mod lock {
+    use std::{fs, process, thread};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use crate::config;
+
+
+    // How long to wait for another copy to finish before giving up.
+    const WAIT: Duration = Duration::from_secs(30);
+
+    static HELD: Mutex<Option<fs::File>> = Mutex::new(None);
+    static LOADED: Mutex<Option<u64>> = Mutex::new(None);
+
+
+    // The word list stays locked until this is dropped.
+    pub struct Guard {
+        // Only the first acquire unlocks it, in case it was locked again
+        // while it was held.
+        owner: bool,
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            if self.owner {
+                // Closing the file is what unlocks it.
+                HELD.lock().expect("Could not unlock the word list").take();
+            }
+        }
+    }
+
+
+    // Locks the word list until the Guard is dropped, waiting a while if
+    // another copy of sear_stone has it. Locking again while it's held does
+    // nothing.
+    pub fn acquire() -> Guard {
+        let mut held = HELD.lock().expect("Could not lock the word list");
+        if held.is_some() {
+            return Guard { owner: false }
+        }
+
+        let _dir = fs::create_dir_all(config::data_dir());
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_file())
+            .expect("Could not open the lock file");
+
+        let start = Instant::now();
+        let mut told = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(fs::TryLockError::WouldBlock) => {
+                    if !told {
+                        eprintln!("The word list is being saved by another sear_stone, waiting for it to finish...");
+                        told = true;
+                    }
+                    if start.elapsed() > WAIT {
+                        eprintln!("It's still busy, so giving up.");
+                        process::exit(1);
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                }
+                Err(fs::TryLockError::Error(e)) => {
+                    eprintln!("Could not lock the word list: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        *held = Some(file);
+        Guard { owner: true }
+    }
+
+
+    // The lock sits next to the word list, since the list itself gets
+    // replaced every time it's saved.
+    fn lock_file() -> PathBuf {
+        let name = config::word_file().file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("word_list.json");
+        config::data_dir().join(format!(".{}.lock", name))
+    }
+
+
+    // Reads the word list while it's locked, for commands that are going to
+    // save it again.
+    pub fn read_words() -> String {
+        let _lock = acquire();
+        let f_words = fs::read_to_string(config::word_file()).expect("Could not read file");
+        *LOADED.lock().expect("Could not lock the word list") = Some(fingerprint(&f_words));
+        f_words
+    }
+
+
+    // Whether now is what was read (or last saved), meaning nothing else
+    // has saved the word list since.
+    pub fn unchanged(now: &str) -> bool {
+        *LOADED.lock().expect("Could not lock the word list") == Some(fingerprint(now))
+    }
+
+
+    // For when the word list was changed in a way that can't be saved on top
+    // of, like an editor leaving it broken. Nothing is saved and the program
+    // ends.
+    pub fn changed() -> ! {
+        eprintln!("The word list was changed by something else after it was loaded, so");
+        eprintln!("this run's changes weren't saved. Run it again to redo them.");
+        process::exit(1);
+    }
+
+
+    // Remember what was just saved, in case it's saved again.
+    pub fn saved(contents: &str) {
+        *LOADED.lock().expect("Could not lock the word list") = Some(fingerprint(contents));
+    }
+
+
+    fn fingerprint(contents: &str) -> u64 {
+        let mut h = DefaultHasher::new();
+        contents.hash(&mut h);
+        h.finish()
+    }
+}
+
+
+// Where the words are actually kept. Everything in processes goes through a
+// WordStore instead of reading and writing the word file itself. There are
+// two kinds, picked with "store" in the config file:
+//  - "json" (the default) keeps everything in one JSON file, which gets
+//    rewritten on every save.
+//  - "sqlite" keeps the words in a SQLite database, so saving only writes
+//    the words that changed. A new database starts with whatever is in the
+//    JSON file next to it.
+// A profile that shares the main word list wraps either one in a
+// ProgressStore, which swaps in the profile's own review progress.
+mod store {
+    use std::{collections, fs, process};
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+    use serde_json::Value;
+    use crate::backup;
+    use crate::config;
+    use crate::lock;
+    use crate::migrate;
+    use crate::profile;
+    use crate::ss_data::WordFilter;
+    use crate::structures::{JPWord, ReviewState, Sentence};
+
+
+    pub trait WordStore {
+        // Reads the words in, upgrading them if they were saved by an older
+        // version. Has to be called before anything else.
+        fn load(&mut self) -> migrate::Report;
+
+        // The word with this key (see kana::identity_key).
+        fn get(&self, key: &str) -> Option<JPWord>;
+
+        // Adds the word, or replaces the one with the same key.
+        fn upsert(&mut self, word: JPWord);
+
+        // Returns whether there was a word to remove.
+        fn remove(&mut self, key: &str) -> bool;
+
+        // Every word that matches, sorted.
+        fn query(&self, filter: &WordFilter) -> Vec<JPWord>;
+
+        // Every example sentence. Words point at them by id.
+        fn sentences(&self) -> Vec<Sentence>;
+
+        // Adds the sentence, or replaces the one with the same id.
+        fn upsert_sentence(&mut self, sentence: Sentence);
+
+        // Writes out everything that changed since load.
+        fn save(&mut self);
+    }
+
+
+    // Opens and loads the store the config asks for. If writing is set, it's
+    // read while the word list is locked (see the lock module), so it can be
+    // saved later.
+    pub fn open(writing: bool) -> Box<dyn WordStore> {
+        open_with_report(writing).0
+    }
+
+
+    // Same as open, but also says what load had to upgrade.
+    pub fn open_with_report(writing: bool) -> (Box<dyn WordStore>, migrate::Report) {
+        let mut store: Box<dyn WordStore> = match config::store().as_str() {
+            config::JSON => Box::new(JsonStore {
+                words: Vec::new(),
+                sentences: Vec::new(),
+                writing,
+                changes: Changes::default(),
+                loaded: collections::HashMap::new(),
+                upgrade: false,
+            }),
+            config::SQLITE => Box::new(SqliteStore {
+                conn: None,
+                words: collections::BTreeMap::new(),
+                removed: collections::BTreeSet::new(),
+                sentences: collections::BTreeMap::new(),
+                loaded: RefCell::new(collections::HashMap::new()),
+            }),
+            other => {
+                eprintln!("Unknown store \"{}\" in the config file. Use json or sqlite.", other);
+                process::exit(1);
+            }
+        };
+        if let Some(file) = profile::progress_file() {
+            store = Box::new(ProgressStore {
+                words: store,
+                file,
+                progress: collections::BTreeMap::new(),
+                main: collections::HashMap::new(),
+                changes: Changes::default(),
+            });
+        }
+        let report = store.load();
+        (store, report)
+    }
+
+
+    // Fills in anything that's worked out from the rest of the word.
+    fn prepare(mut w: JPWord) -> JPWord {
+        w.fill_reading();
+        w
+    }
+
+
+    // Puts this run's changes to a word on top of theirs, the word as
+    // something else saved it. base is the word as this run loaded it, so
+    // only the fields that differ from it count as changed here. If both
+    // changed the same field, this run's version is kept and the user is
+    // told. None means the word is removed.
+    fn merge(base: Option<&JPWord>, ours: Option<&JPWord>, theirs: Option<&JPWord>) -> Option<JPWord> {
+        let as_value = |w: &JPWord| serde_json::to_value(w).expect("Could not parse into JSON");
+        let changed_there = theirs.map(as_value) != base.map(as_value);
+
+        let (ours, base, theirs) = match (ours, base, theirs) {
+            (None, _, theirs) => {
+                if let Some(w) = theirs.filter(|_| changed_there) {
+                    eprintln!("{} was changed by another sear_stone, but this run removed it, so it's gone.", w.word);
+                }
+                return None
+            }
+            (Some(ours), _, _) if !changed_there => return Some(ours.clone()),
+            (Some(ours), _, None) => {
+                eprintln!("{} was removed by another sear_stone, but this run changed it, so it's kept.", ours.word);
+                return Some(ours.clone())
+            }
+            (Some(ours), None, Some(theirs)) => {
+                if ours != theirs {
+                    eprintln!("{} was added by another sear_stone too. Kept this run's version.", ours.word);
+                }
+                return Some(ours.clone())
+            }
+            (Some(ours), Some(base), Some(theirs)) => (ours, base, theirs),
+        };
+
+        let (Value::Object(base), Value::Object(ours_fields), Value::Object(mut merged)) =
+            (as_value(base), as_value(ours), as_value(theirs)) else {
+            return Some(ours.clone())
+        };
+        let mut both: Vec<String> = Vec::new();
+        for (field, value) in ours_fields {
+            if base.get(&field) == Some(&value) {
+                continue;
             }
+            if merged.get(&field) != base.get(&field) && merged.get(&field) != Some(&value) {
+                both.push(field.clone());
+            }
+            merged.insert(field, value);
+        }
+        if !both.is_empty() {
+            eprintln!("{} was changed by another sear_stone too. Kept this run's {}.", ours.word, both.join(", "));
         }
+        serde_json::from_value(Value::Object(merged)).ok().map(prepare)
+    }
 
-        pub fn get_review(&self) -> bool {
-            let a = match self.to_review {
-                true => true,
-                false => false,
+
+    // Gives the sentences this run added ids that are free in saved, the
+    // sentences that are there now. A sentence that's already saved under
+    // another id uses that one instead. Returns the ids that changed, so the
+    // words pointing at them can be fixed (see renumber).
+    fn place_sentences(ours: &mut [Sentence], saved: &[Sentence]) -> collections::HashMap<u32, u32> {
+        let mut next = saved.iter().chain(ours.iter()).map(|s| s.id + 1).max().unwrap_or(0);
+        let mut ids = collections::HashMap::new();
+        for s in ours.iter_mut() {
+            if saved.iter().any(|t| t.id == s.id && t.text == s.text) {
+                continue;
+            }
+            let id = match saved.iter().find(|t| t.text == s.text) {
+                Some(t) => {
+                    if s.translation.is_none() {
+                        s.translation = t.translation.clone();
+                    }
+                    t.id
+                }
+                None if saved.iter().any(|t| t.id == s.id) => {
+                    next += 1;
+                    next - 1
+                }
+                None => continue,
             };
-            a
+            ids.insert(s.id, id);
+            s.id = id;
         }
+        ids
     }
 
-    impl PartialOrd for JPWord {
-        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-            Some(self.cmp(other))
+
+    // Points a word at the sentences' new ids.
+    fn renumber(w: &mut JPWord, ids: &collections::HashMap<u32, u32>) {
+        for id in w.sentences.iter_mut() {
+            if let Some(new) = ids.get(id) {
+                *id = *new;
+            }
         }
     }
-        
-    impl Ord for JPWord {
-        fn cmp(&self, other: &Self) -> Ordering {
-            self.word.cmp(&other.word)
+
+
+    // What was changed since the store was loaded, by key (or id for
+    // sentences). If something else saved in the meantime, only these get
+    // put on top of what it saved.
+    #[derive(Default)]
+    struct Changes {
+        upserted: collections::BTreeSet<String>,
+        removed: collections::BTreeSet<String>,
+        sentences: collections::BTreeSet<u32>,
+    }
+
+    impl Changes {
+        fn upsert(&mut self, key: String) {
+            self.removed.remove(&key);
+            self.upserted.insert(key);
+        }
+
+        fn remove(&mut self, key: &str) {
+            self.upserted.remove(key);
+            self.removed.insert(key.to_string());
+        }
+
+        // Whether a word that's in the file now should be replaced or
+        // dropped.
+        fn touches(&self, key: &str) -> bool {
+            self.upserted.contains(key) || self.removed.contains(key)
+        }
+
+        fn is_empty(&self) -> bool {
+            self.upserted.is_empty() && self.removed.is_empty() && self.sentences.is_empty()
         }
     }
 
-    impl fmt::Display for JPWord {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            write!(f, "{}", self.word)
+
+    pub struct JsonStore {
+        words: Vec<JPWord>,
+        sentences: Vec<Sentence>,
+        writing: bool,
+        changes: Changes,
+        // Each changed word as it was loaded, or None if it wasn't there.
+        loaded: collections::HashMap<String, Option<JPWord>>,
+        // Whether the file was saved by an older version, so it needs to be
+        // written even if nothing else changed.
+        upgrade: bool,
+    }
+
+    impl JsonStore {
+        // Something else saved the word list after it was loaded. Starts
+        // over from what's there now, and redoes this run's changes on top.
+        fn rebase(&mut self, now: &str) {
+            let (theirs, saved) = if now.trim().is_empty() {
+                (Vec::new(), Vec::new())
+            } else {
+                match migrate::load(now) {
+                    Ok((c, _)) => (c.words, c.sentences),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        lock::changed();
+                    }
+                }
+            };
+
+            // Sentences first, since the words point at them by id.
+            let mut ours: Vec<Sentence> = self.sentences.iter()
+                .filter(|s| self.changes.sentences.contains(&s.id))
+                .cloned()
+                .collect();
+            let ids = place_sentences(&mut ours, &saved);
+            let mut sentences: Vec<Sentence> = saved.into_iter()
+                .filter(|s| !ours.iter().any(|o| o.id == s.id))
+                .collect();
+            sentences.extend(ours);
+            for w in self.words.iter_mut() {
+                renumber(w, &ids);
+            }
+
+            let mut theirs: collections::HashMap<String, JPWord> = theirs.into_iter()
+                .map(prepare)
+                .map(|w| (w.key(), w))
+                .collect();
+            let mut words: Vec<JPWord> = Vec::new();
+            for key in self.changes.upserted.iter().chain(&self.changes.removed) {
+                let base = self.loaded.get(key).and_then(Option::as_ref);
+                let ours = self.words.iter().find(|w| w.key() == *key);
+                words.extend(merge(base, ours, theirs.remove(key).as_ref()));
+            }
+            words.extend(theirs.into_values());
+
+            self.words = words;
+            self.sentences = sentences;
+        }
+
+        // Keeps the word as it was loaded, the first time it's changed.
+        fn remember(&mut self, key: &str) {
+            if !self.loaded.contains_key(key) {
+                let w = self.get(key);
+                self.loaded.insert(key.to_string(), w);
+            }
         }
     }
 
+    impl WordStore for JsonStore {
+        fn load(&mut self) -> migrate::Report {
+            let file = config::word_file();
+            if !file.exists() {
+                let _dir = fs::create_dir_all(config::data_dir());
+                let _file = fs::File::create(file)
+                    .expect("Could not create file");
+            }
 
-    // JapaneseWordType is used to help the program know when what it's 
-    // reading is Hiragana, Katakana, or neither. It's important, because 
-    // words must be of the same type. Once the type changes, it must be a new
-    // word, or not a word at all.
-    #[derive(PartialEq)]
-    pub enum JapaneseWordType {
-        Hiragana,
-        Katakana,
-        Neither,
+            let f_words = if self.writing {
+                lock::read_words()
+            } else {
+                fs::read_to_string(file).expect("Could not read file")
+            };
+            if f_words.trim().is_empty() {
+                return migrate::Report { from: migrate::VERSION, changes: Vec::new() }
+            }
+
+            let (contents, report) = match migrate::load(&f_words) {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            };
+            self.words = contents.words.into_iter().map(prepare).collect();
+            self.sentences = contents.sentences;
+            self.upgrade = report.from < migrate::VERSION;
+            report
+        }
+
+        fn get(&self, key: &str) -> Option<JPWord> {
+            self.words.iter().find(|w| w.key() == key).cloned()
+        }
+
+        fn upsert(&mut self, word: JPWord) {
+            self.remember(&word.key());
+            self.changes.upsert(word.key());
+            match self.words.iter_mut().find(|w| w.key() == word.key()) {
+                Some(w) => *w = word,
+                None => self.words.push(word),
+            }
+        }
+
+        fn remove(&mut self, key: &str) -> bool {
+            self.remember(key);
+            self.changes.remove(key);
+            let before = self.words.len();
+            self.words.retain(|w| w.key() != key);
+            self.words.len() != before
+        }
+
+        fn query(&self, filter: &WordFilter) -> Vec<JPWord> {
+            let mut words: Vec<JPWord> = self.words.iter()
+                .filter(|w| filter.matches(w))
+                .cloned()
+                .collect();
+            words.sort();
+            words
+        }
+
+        fn sentences(&self) -> Vec<Sentence> {
+            self.sentences.clone()
+        }
+
+        fn upsert_sentence(&mut self, sentence: Sentence) {
+            self.changes.sentences.insert(sentence.id);
+            match self.sentences.iter_mut().find(|s| s.id == sentence.id) {
+                Some(s) => *s = sentence,
+                None => self.sentences.push(sentence),
+            }
+        }
+
+        // Only writes (and backs up) the file if something changed, so a run
+        // that didn't change anything doesn't push out a real backup.
+        fn save(&mut self) {
+            if self.changes.is_empty() && !self.upgrade {
+                return
+            }
+
+            let _lock = lock::acquire();
+            let now = fs::read_to_string(config::word_file()).unwrap_or_default();
+            if !lock::unchanged(&now) {
+                self.rebase(&now);
+            }
+
+            self.words.sort();
+            self.sentences.sort_by_key(|s| s.id);
+            let stringified = migrate::to_string(&self.words, &self.sentences);
+            backup::save_words(&stringified)
+                .expect("Could not write to file");
+            self.changes = Changes::default();
+            self.loaded.clear();
+            self.upgrade = false;
+        }
     }
 
 
-    // JapaneseWordParser is used to parser together japanese words. It's 
-    // meant to be used by feeding it a stream of chars. Based on the input
-    // of chars, it will either 1) add the char to the word, 2) do nothing
-    // if it is not a word, or 3) return a word if the chars change type.
-    pub struct JapaneseWordParser {
-        pub word_type: JapaneseWordType,
-        pub word: String,
-        pub changed: bool,
+    // Each word is a row, with the word itself saved as JSON so it can be
+    // upgraded the same way the JSON file is. Changes are kept here until
+    // save writes them all in one transaction, so the database isn't locked
+    // while the user is thinking.
+    pub struct SqliteStore {
+        conn: Option<Connection>,
+        // Words and sentences that were changed since load, and words that
+        // were removed.
+        words: collections::BTreeMap<String, JPWord>,
+        removed: collections::BTreeSet<String>,
+        sentences: collections::BTreeMap<u32, Sentence>,
+        // Each word as it was first read from the database (or last saved),
+        // or None if it wasn't there. The words are read as they're needed,
+        // so this is what this run's changes were made to. Words that were
+        // only upgraded aren't in here.
+        loaded: RefCell<collections::HashMap<String, Option<JPWord>>>,
     }
 
-    impl JapaneseWordParser {
-        pub fn new() -> Self {
-            JapaneseWordParser {
-                word_type: JapaneseWordType::Neither,
-                word: String::new(),
-                changed: false,
+    impl SqliteStore {
+        fn conn(&self) -> &Connection {
+            self.conn.as_ref().expect("The store has to be loaded first")
+        }
+
+
+        // Copies the words and sentences from the JSON word list next to the
+        // database, if there is one. Returns what had to be upgraded on the
+        // way. The JSON file is left alone.
+        fn import(&mut self) -> Option<migrate::Report> {
+            let json = config::word_file().with_extension(config::JSON);
+            let f_words = fs::read_to_string(&json).ok().filter(|f| !f.trim().is_empty())?;
+            let (contents, report) = match migrate::load(&f_words) {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("Could not copy the words over from {}: {}", json.display(), e);
+                    process::exit(1);
+                }
+            };
+
+            let count = contents.words.len();
+            for w in contents.words {
+                self.upsert(w);
+            }
+            for s in contents.sentences {
+                self.upsert_sentence(s);
             }
+            let _lock = lock::acquire();
+            self.commit();
+            eprintln!("Copied {} words from {} into {}.", count, json.display(), config::word_file().display());
+            Some(report)
         }
 
-        // main parser. Takes in a char, returns nothing if it's the same type,
-        // adds a char to the current word being processes 
-        // (JapaneseWordParser.word) or it returns a new word if the type has
-        // changed.
-        pub fn add_to_word(&mut self, s: char ) -> Option<String> {
-            if s >= '\u{3040}' && s <= '\u{309f}' {
-                if self.word_type != JapaneseWordType::Hiragana {
-                    return self.switch_word_type(JapaneseWordType::Hiragana, s)
-                } 
-                self.word.push(s);
-                None
-            } else if s >= '\u{30A0}' && s <= '\u{30FF}' {
-                if self.word_type != JapaneseWordType::Katakana {
-                    return self.switch_word_type(JapaneseWordType::Katakana, s)
+
+        // Writes every change in one transaction. Another sear_stone could
+        // have saved since this one loaded, so only what changed here is put
+        // on top of what's there (see merge).
+        fn commit(&mut self) {
+            let conn = self.conn.as_mut().expect("The store has to be loaded first");
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)
+                .unwrap_or_else(|e| fail(e));
+
+            // Sentences first, since the words point at them by id.
+            let saved: Vec<Sentence> = read_all(&tx, "SELECT data FROM sentences");
+            let mut sentences: Vec<Sentence> = std::mem::take(&mut self.sentences).into_values().collect();
+            let ids = place_sentences(&mut sentences, &saved);
+            for sentence in &sentences {
+                let data = serde_json::to_string(sentence).expect("Could not parse into JSON before writing");
+                tx.execute(
+                    "INSERT INTO sentences (id, data) VALUES (?1, ?2)
+                     ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+                    params![sentence.id, data],
+                ).unwrap_or_else(|e| fail(e));
+            }
+
+            let saved_word = |key: &str| -> Option<JPWord> {
+                let data: Option<String> = tx
+                    .query_row("SELECT data FROM words WHERE key = ?1", params![key], |r| r.get(0))
+                    .optional()
+                    .unwrap_or_else(|e| fail(e));
+                data.map(|d| prepare(serde_json::from_str(&d).expect("Could not parse json from the database")))
+            };
+            let loaded = self.loaded.get_mut();
+            let mut merged: Vec<(&String, Option<JPWord>)> = Vec::new();
+            for key in &self.removed {
+                // Only to tell the user if it was changed there, it's
+                // removed either way.
+                if let Some(base) = loaded.get(key) {
+                    merge(base.as_ref(), None, saved_word(key).as_ref());
                 }
-                self.word.push(s);
-                None
-            } else {
-                match self.switch_word_type(JapaneseWordType::Neither, ' ') {
-                    None => None,
-                    Some(s) => {
-                        return Some(s)
+                merged.push((key, None));
+            }
+            for (key, word) in self.words.iter_mut() {
+                renumber(word, &ids);
+                let word = match loaded.get(key) {
+                    Some(base) => merge(base.as_ref(), Some(word), saved_word(key).as_ref()),
+                    None => Some(word.clone()),
+                };
+                merged.push((key, word));
+            }
+
+            for (key, word) in merged {
+                match &word {
+                    Some(word) => {
+                        let data = serde_json::to_string(word).expect("Could not parse into JSON before writing");
+                        tx.execute(
+                            "INSERT INTO words (key, word, data) VALUES (?1, ?2, ?3)
+                             ON CONFLICT(key) DO UPDATE SET word = excluded.word, data = excluded.data",
+                            params![key, word.word, data],
+                        ).unwrap_or_else(|e| fail(e));
+                    }
+                    None => {
+                        tx.execute("DELETE FROM words WHERE key = ?1", params![key])
+                            .unwrap_or_else(|e| fail(e));
                     }
                 }
+                loaded.insert(key.clone(), word);
             }
+            tx.execute(
+                "INSERT OR REPLACE INTO meta (name, value) VALUES ('version', ?1)",
+                params![migrate::VERSION],
+            ).unwrap_or_else(|e| fail(e));
+            tx.commit().unwrap_or_else(|e| fail(e));
+
+            self.words.clear();
+            self.removed.clear();
         }
 
+        // Makes sure the word as it was before this run changed it is in
+        // loaded.
+        fn remember(&self, key: &str) {
+            if !self.loaded.borrow().contains_key(key) {
+                self.get(key);
+            }
+        }
+    }
 
-        // used by JapaneseWordParser.add_to_word  If the type changes, it 
-        // must do different things based on the previous type. 
-        fn switch_word_type(&mut self, t: JapaneseWordType, c: char) -> Option<String> {
-            // The inital if covers the case that the first char is of a certain type. 
-            // without it, you would get all the words back w/o the first
-            // character, which is pointless
-            //
-            // first if else covers if it encounters a non japanese char, if 
-            // there is something to return, return it.
-            // todo:
-            // - [ ] Find out what happens when a katakana is next to a 
-            //          hiragana char, or vis-versa. Does it end up returning
-            //          a mixed hiragana and katakana word?
-            //  
-            //  the else statment covers if nothing has changed, just add the
-            //  char to the word.
-            if self.word_type != t {
-                self.changed = true;
-                self.word.push(c);
-                None
-            } else if t == JapaneseWordType::Neither {
-                self.word_type = t;
-                let final_word = &self.word;
-                if final_word == "" {
-                    None
-                } else {
-                    Some(final_word.to_string())
+    // Only errors that mean the database is broken end up here.
+    fn fail(e: rusqlite::Error) -> ! {
+        eprintln!("Something went wrong with the word database: {}", e);
+        process::exit(1);
+    }
+
+    impl WordStore for SqliteStore {
+        fn load(&mut self) -> migrate::Report {
+            let _dir = fs::create_dir_all(config::data_dir());
+            let conn = Connection::open(config::word_file()).unwrap_or_else(|e| fail(e));
+            // Another sear_stone could be saving right now.
+            conn.busy_timeout(Duration::from_secs(30)).unwrap_or_else(|e| fail(e));
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS words (
+                    key TEXT PRIMARY KEY,
+                    word TEXT NOT NULL,
+                    data TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS sentences (
+                    id INTEGER PRIMARY KEY,
+                    data TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS meta (
+                    name TEXT PRIMARY KEY,
+                    value INTEGER NOT NULL
+                );",
+            ).unwrap_or_else(|e| fail(e));
+
+            let version: Option<u64> = conn
+                .query_row("SELECT value FROM meta WHERE name = 'version'", [], |r| r.get(0))
+                .optional()
+                .unwrap_or_else(|e| fail(e));
+            self.conn = Some(conn);
+
+            // A brand new database starts with the JSON word list, if there's
+            // one next to it, so switching to sqlite doesn't start over.
+            if version.is_none() {
+                if let Some(report) = self.import() {
+                    return report
+                }
+            }
+            // Otherwise it's already up to date.
+            let from = version.unwrap_or(migrate::VERSION);
+
+            let mut words: Vec<Value> = Vec::new();
+            if from < migrate::VERSION {
+                words = read_all(self.conn(), "SELECT data FROM words");
+            }
+            let report = match migrate::upgrade(&mut words, from) {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            };
+
+            // The upgraded words are saved along with everything else.
+            if !report.changes.is_empty() {
+                for w in words {
+                    let w: JPWord = serde_json::from_value(w).expect("Could not read the words");
+                    self.words.insert(w.key(), w);
                 }
-            } else {
-                self.changed = false;
-                self.word.push(c);
-                None
             }
+            report
+        }
+
+        fn get(&self, key: &str) -> Option<JPWord> {
+            if self.removed.contains(key) {
+                return None
+            }
+            if let Some(w) = self.words.get(key) {
+                return Some(w.clone())
+            }
+            let data: Option<String> = self.conn()
+                .query_row("SELECT data FROM words WHERE key = ?1", params![key], |r| r.get(0))
+                .optional()
+                .unwrap_or_else(|e| fail(e));
+            let w = data.map(|d| prepare(serde_json::from_str(&d).expect("Could not parse json from the database")));
+            self.loaded.borrow_mut().entry(key.to_string()).or_insert_with(|| w.clone());
+            w
+        }
+
+        fn upsert(&mut self, word: JPWord) {
+            self.remember(&word.key());
+            self.removed.remove(&word.key());
+            self.words.insert(word.key(), word);
+        }
+
+        fn remove(&mut self, key: &str) -> bool {
+            self.remember(key);
+            let there = self.get(key).is_some();
+            self.words.remove(key);
+            if there {
+                self.removed.insert(key.to_string());
+            }
+            there
+        }
+
+        fn query(&self, filter: &WordFilter) -> Vec<JPWord> {
+            let mut stmt = self.conn()
+                .prepare("SELECT key, data FROM words")
+                .unwrap_or_else(|e| fail(e));
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))
+                .unwrap_or_else(|e| fail(e));
+
+            let mut words: Vec<JPWord> = Vec::new();
+            for row in rows {
+                let (key, data) = row.unwrap_or_else(|e| fail(e));
+                if self.words.contains_key(&key) || self.removed.contains(&key) {
+                    continue;
+                }
+                let w = prepare(serde_json::from_str(&data).expect("Could not parse json from the database"));
+                self.loaded.borrow_mut().entry(key).or_insert_with(|| Some(w.clone()));
+                if filter.matches(&w) {
+                    words.push(w);
+                }
+            }
+            words.extend(self.words.values().filter(|w| filter.matches(w)).cloned());
+            words.sort();
+            words
+        }
+
+        fn sentences(&self) -> Vec<Sentence> {
+            let mut sentences: collections::BTreeMap<u32, Sentence> =
+                read_all::<Sentence>(self.conn(), "SELECT data FROM sentences")
+                    .into_iter()
+                    .map(|s| (s.id, s))
+                    .collect();
+            sentences.extend(self.sentences.iter().map(|(id, s)| (*id, s.clone())));
+            sentences.into_values().collect()
+        }
+
+        fn upsert_sentence(&mut self, sentence: Sentence) {
+            self.sentences.insert(sentence.id, sentence);
+        }
+
+        // The database is backed up first, but only if there's something to
+        // save.
+        fn save(&mut self) {
+            if self.words.is_empty() && self.removed.is_empty() && self.sentences.is_empty() {
+                return
+            }
+
+            let _lock = lock::acquire();
+            backup::backup_current().expect("Could not back up the word list");
+            self.commit();
+        }
+    }
+
+
+    // Reads every row of a query that returns JSON.
+    fn read_all<T: serde::de::DeserializeOwned>(conn: &Connection, sql: &str) -> Vec<T> {
+        let mut stmt = conn.prepare(sql).unwrap_or_else(|e| fail(e));
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0)).unwrap_or_else(|e| fail(e));
+        rows.map(|data| {
+            let data = data.unwrap_or_else(|e| fail(e));
+            serde_json::from_str(&data).expect("Could not parse json from the database")
+        }).collect()
+    }
+
+
+    // The words come from the main list, but the review progress comes from
+    // the profile. Words the profile hasn't reviewed yet start over like
+    // they were just added.
+    pub struct ProgressStore {
+        words: Box<dyn WordStore>,
+        file: PathBuf,
+        progress: collections::BTreeMap<String, ReviewState>,
+        // The main list's own progress, which has to be kept when a word is
+        // saved.
+        main: collections::HashMap<String, ReviewState>,
+        // Whose progress changed, in case the same profile is being used in
+        // another terminal.
+        changes: Changes,
+    }
+
+    impl ProgressStore {
+        fn with_progress(&self, mut w: JPWord) -> JPWord {
+            w.set_review_state(self.progress.get(&w.key()).cloned().unwrap_or_default());
+            w
+        }
+
+        fn read(&self) -> collections::BTreeMap<String, ReviewState> {
+            match fs::read_to_string(&self.file) {
+                Ok(contents) if !contents.trim().is_empty() => serde_json::from_str(&contents)
+                    .expect("Could not parse the review progress"),
+                _ => collections::BTreeMap::new(),
+            }
+        }
+    }
+
+    impl WordStore for ProgressStore {
+        fn load(&mut self) -> migrate::Report {
+            let report = self.words.load();
+            self.main = self.words.query(&WordFilter::default())
+                .into_iter()
+                .map(|w| (w.key(), w.review_state()))
+                .collect();
+            self.progress = self.read();
+            report
+        }
+
+        fn get(&self, key: &str) -> Option<JPWord> {
+            self.words.get(key).map(|w| self.with_progress(w))
+        }
+
+        fn upsert(&mut self, mut word: JPWord) {
+            let key = word.key();
+            self.changes.upsert(key.clone());
+            self.progress.insert(key.clone(), word.review_state());
+            word.set_review_state(self.main.get(&key).cloned().unwrap_or_default());
+            self.main.insert(key, word.review_state());
+            self.words.upsert(word);
+        }
+
+        fn remove(&mut self, key: &str) -> bool {
+            self.changes.remove(key);
+            self.progress.remove(key);
+            self.main.remove(key);
+            self.words.remove(key)
+        }
+
+        fn query(&self, filter: &WordFilter) -> Vec<JPWord> {
+            self.words.query(filter)
+                .into_iter()
+                .map(|w| self.with_progress(w))
+                .collect()
+        }
+
+        fn sentences(&self) -> Vec<Sentence> {
+            self.words.sentences()
+        }
+
+        fn upsert_sentence(&mut self, sentence: Sentence) {
+            self.words.upsert_sentence(sentence);
+        }
+
+        // The word list's lock covers this file too. Only the progress that
+        // changed is put on top of what's saved now.
+        fn save(&mut self) {
+            let _lock = lock::acquire();
+            self.words.save();
+            if self.changes.is_empty() {
+                return
+            }
+
+            let mut progress = self.read();
+            progress.retain(|key, _| !self.changes.touches(key));
+            for key in &self.changes.upserted {
+                if let Some(state) = self.progress.get(key) {
+                    progress.insert(key.clone(), state.clone());
+                }
+            }
+            self.progress = progress;
+            self.changes = Changes::default();
+
+            let stringified = serde_json::to_string(&self.progress)
+                .expect("Could not parse into JSON before writing");
+            backup::write_atomic(&self.file, stringified)
+                .expect("Could not write to file");
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::structures::Sense;
+
+        fn sentence(id: u32, text: &str) -> Sentence {
+            Sentence { id, text: text.to_string(), translation: None, source: String::new() }
+        }
+
+        #[test]
+        fn merges_fields() {
+            let base = JPWord::simple_new(String::from("たべる"));
+            // This run added a meaning, the other one added a tag.
+            let mut ours = base.clone();
+            ours.senses.push(Sense::new("to eat"));
+            let mut theirs = base.clone();
+            theirs.tags.push(String::from("food"));
+
+            let merged = merge(Some(&base), Some(&ours), Some(&theirs)).unwrap();
+            assert_eq!(merged.senses, ours.senses);
+            assert_eq!(merged.tags, theirs.tags);
+
+            // Nothing changed there, so this run's copy is used as is.
+            assert_eq!(merge(Some(&base), Some(&ours), Some(&base)), Some(ours.clone()));
+            // Removed here.
+            assert_eq!(merge(Some(&base), None, Some(&theirs)), None);
+            // Removed there, but changed here.
+            assert_eq!(merge(Some(&base), Some(&ours), None), Some(ours.clone()));
+
+            // Both changed the tags, this run's win.
+            let mut ours = base.clone();
+            ours.tags.push(String::from("verbs"));
+            let merged = merge(Some(&base), Some(&ours), Some(&theirs)).unwrap();
+            assert_eq!(merged.tags, ours.tags);
+        }
+
+        #[test]
+        fn places_sentences() {
+            let saved = [sentence(0, "a"), sentence(1, "b"), sentence(2, "c")];
+            // 1 is already saved, 2 was taken by something else, and 3 was
+            // saved as 0 by the other run.
+            let mut ours = [sentence(1, "b"), sentence(2, "d"), sentence(3, "a"), sentence(5, "e")];
+            let ids = place_sentences(&mut ours, &saved);
+
+            let placed: Vec<(u32, &str)> = ours.iter().map(|s| (s.id, s.text.as_str())).collect();
+            assert_eq!(placed, [(1, "b"), (6, "d"), (0, "a"), (5, "e")]);
+            assert_eq!(ids, collections::HashMap::from([(2, 6), (3, 0)]));
+
+            let mut w = JPWord::simple_new(String::from("たべる"));
+            w.sentences = vec![1, 2, 3];
+            renumber(&mut w, &ids);
+            assert_eq!(w.sentences, [1, 6, 0]);
         }
     }
 }